@@ -0,0 +1,292 @@
+use crate::gfx_prelude::*;
+use ggez::mint::Point2;
+use std::collections::{HashMap, VecDeque};
+
+// Tiles per second the player and boxes move at, unless configured otherwise
+pub const DEFAULT_ANIMATION_SPEED: f32 = 12.0;
+
+// Moves that pile up beyond this are shown faster, so that the board never
+// lags far behind the game
+const CATCH_UP_STEPS: usize = 8;
+
+// Sparks flying off a box when it lands on a target
+const LANDING_TIME: f32 = 0.5;
+const LANDING_COLOR: Color = Color::new(1.0, 0.85, 0.2, 1.0);
+
+// Bursts over every box once the level is solved, one after another
+const CELEBRATION_TIME: f32 = 0.9;
+const CELEBRATION_DELAY: f32 = 0.08;
+const CELEBRATION_COLORS: [Color; 4] = [
+    Color::new(1.0, 0.85, 0.2, 1.0),
+    Color::new(0.3, 0.9, 0.4, 1.0),
+    Color::new(0.3, 0.6, 1.0, 1.0),
+    Color::new(1.0, 0.4, 0.6, 1.0),
+];
+const SPARKS: usize = 8;
+
+// A move as it is shown. Undone moves are shown backwards.
+#[derive(Clone, Copy, Debug)]
+struct Step {
+    delta: Point2D,
+    box_id: Option<Uuid>,
+    undo: bool,
+}
+
+// Sparks flying outwards from the center of a tile
+#[derive(Clone, Copy, Debug)]
+struct Burst {
+    tile: Point2D,
+    color: Color,
+    // Seconds since it started, negative while it waits to start
+    age: f32,
+    duration: f32,
+}
+
+// Moves the player and the boxes smoothly from tile to tile. The game itself
+// moves from one position to the next right away, so the animator follows
+// its moves and shows them one after another at its own pace.
+pub struct Animator {
+    // Tiles per second, or 0 to show every move right away
+    speed: f32,
+    // Where the player and the boxes are shown before the step in progress
+    player: Point2D,
+    boxes: HashMap<Uuid, Point2D>,
+    // The moves of the game that led to the position shown after all steps
+    moves: Vec<PlayerMove>,
+    steps: VecDeque<Step>,
+    // How far the first step has got, from 0 to 1
+    progress: f32,
+    bursts: Vec<Burst>,
+    celebrating: bool,
+}
+
+impl Default for Animator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Self {
+            speed: DEFAULT_ANIMATION_SPEED,
+            player: Point2D { x: 0, y: 0 },
+            boxes: HashMap::new(),
+            moves: Vec::new(),
+            steps: VecDeque::new(),
+            progress: 0.0,
+            bursts: Vec::new(),
+            celebrating: false,
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    // Shows the position of `game` as it is, e.g. after starting a level.
+    pub fn reset(&mut self, game: &Game) {
+        self.moves = game.moves().to_vec();
+        self.steps.clear();
+        self.progress = 0.0;
+        self.bursts.clear();
+        self.celebrating = false;
+        self.snap(game);
+    }
+
+    // Celebrates the solved level once the last moves have been shown.
+    pub fn celebrate(&mut self) {
+        self.celebrating = true;
+    }
+
+    // Whether anything is still moving or sparkling.
+    pub fn is_busy(&self) -> bool {
+        !self.steps.is_empty() || !self.bursts.is_empty() || self.celebrating
+    }
+
+    // Picks up the moves made or undone since the last frame and advances
+    // the animations by `dt` seconds.
+    pub fn update(&mut self, game: &Game, dt: f32) {
+        self.follow(game.moves());
+
+        let rate = if self.speed > 0.0 {
+            self.speed * (self.steps.len() as f32 / CATCH_UP_STEPS as f32).max(1.0)
+        } else {
+            f32::INFINITY
+        };
+
+        self.progress += dt * rate;
+
+        while self.progress >= 1.0 {
+            let Some(step) = self.steps.pop_front() else {
+                break;
+            };
+
+            self.finish(step, &game.level().targets);
+            self.progress -= 1.0;
+        }
+
+        if self.steps.is_empty() {
+            self.progress = 0.0;
+
+            // Whatever happened, the board ends up where the game is
+            self.snap(game);
+
+            if self.celebrating {
+                self.celebrating = false;
+                self.start_celebration(game);
+            }
+        }
+
+        for burst in &mut self.bursts {
+            burst.age += dt;
+        }
+
+        self.bursts.retain(|b| b.age < b.duration);
+    }
+
+    // Where the player is shown, in tiles.
+    pub fn player_position(&self) -> Point2<f32> {
+        self.shown(self.player, |_| true)
+    }
+
+    // Where a box is shown, in tiles, or `None` for a box the animator
+    // doesn't know yet.
+    pub fn box_position(&self, id: &Uuid) -> Option<Point2<f32>> {
+        let position = *self.boxes.get(id)?;
+
+        Some(self.shown(position, |step| step.box_id == Some(*id)))
+    }
+
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas, offset: Point2D) -> GameResult {
+        let mut mb = MeshBuilder::new();
+        let mut empty = true;
+
+        for burst in self.bursts.iter().filter(|b| b.age >= 0.0) {
+            let t = burst.age / burst.duration;
+            let center = Point2 {
+                x: (TILE_WIDTH as f32) * (burst.tile.x + offset.x) as f32 + TILE_WIDTH as f32 / 2.0,
+                y: (TILE_HEIGHT as f32) * (burst.tile.y + offset.y) as f32
+                    + TILE_HEIGHT as f32 / 2.0,
+            };
+            let distance = TILE_WIDTH as f32 * (0.3 + 0.7 * t);
+            let size = 6.0 * (1.0 - t) + 2.0;
+            let mut color = burst.color;
+
+            color.a = 1.0 - t;
+
+            for i in 0..SPARKS {
+                let angle = i as f32 * std::f32::consts::TAU / SPARKS as f32;
+
+                mb.rectangle(
+                    DrawMode::fill(),
+                    Rect::new(
+                        center.x + distance * angle.cos() - size / 2.0,
+                        center.y + distance * angle.sin() - size / 2.0,
+                        size,
+                        size,
+                    ),
+                    color,
+                )?;
+                empty = false;
+            }
+        }
+
+        // Meshes without any vertices can't be drawn
+        if !empty {
+            canvas.draw(&Mesh::from_data(ctx, mb.build()), DrawParam::default());
+        }
+
+        Ok(())
+    }
+
+    // Queues the difference between the moves shown and the moves of the
+    // game: undone moves backwards, then new moves forwards.
+    fn follow(&mut self, moves: &[PlayerMove]) {
+        let common = self
+            .moves
+            .iter()
+            .zip(moves)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        if common == self.moves.len() && common == moves.len() {
+            return;
+        }
+
+        for undone in self.moves[common..].iter().rev() {
+            self.steps.push_back(Step {
+                delta: Point2D { x: 0, y: 0 } - undone.delta,
+                box_id: undone.box_id,
+                undo: true,
+            });
+        }
+
+        for made in &moves[common..] {
+            self.steps.push_back(Step {
+                delta: made.delta,
+                box_id: made.box_id,
+                undo: false,
+            });
+        }
+
+        self.moves = moves.to_vec();
+    }
+
+    fn finish(&mut self, step: Step, targets: &[Point2D]) {
+        self.player += step.delta;
+
+        if let Some(position) = step.box_id.and_then(|id| self.boxes.get_mut(&id)) {
+            *position += step.delta;
+
+            if !step.undo && targets.contains(position) {
+                self.bursts.push(Burst {
+                    tile: *position,
+                    color: LANDING_COLOR,
+                    age: 0.0,
+                    duration: LANDING_TIME,
+                });
+            }
+        }
+    }
+
+    fn start_celebration(&mut self, game: &Game) {
+        for (i, b) in game.boxes().iter().enumerate() {
+            self.bursts.push(Burst {
+                tile: *b.get_position(),
+                color: CELEBRATION_COLORS[i % CELEBRATION_COLORS.len()],
+                age: -(i as f32) * CELEBRATION_DELAY,
+                duration: CELEBRATION_TIME,
+            });
+        }
+    }
+
+    fn snap(&mut self, game: &Game) {
+        self.player = game.player().position;
+        self.boxes = game
+            .boxes()
+            .iter()
+            .map(|b| (*b.get_id(), *b.get_position()))
+            .collect();
+    }
+
+    // A position plus the part of the step in progress, if that step moves
+    // the thing shown there.
+    fn shown(&self, position: Point2D, is_moved: impl Fn(&Step) -> bool) -> Point2<f32> {
+        let mut shown: Point2<f32> = position.into();
+
+        if let Some(step) = self.steps.front().filter(|s| is_moved(s)) {
+            // Eases in and out of every tile
+            let t = self.progress * self.progress * (3.0 - 2.0 * self.progress);
+
+            shown.x += step.delta.x as f32 * t;
+            shown.y += step.delta.y as f32 * t;
+        }
+
+        shown
+    }
+}
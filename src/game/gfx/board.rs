@@ -0,0 +1,169 @@
+use crate::gfx_prelude::*;
+use ggez::mint::Point2;
+
+// Draws the tiles of the level, leaving out the exterior.
+pub fn draw_level(level: &Level, sprites: &SpriteManager, canvas: &mut Canvas, offset: Point2D) {
+    let bg_image = sprites.get_sprite("floor").unwrap();
+
+    for y in 0..level.height {
+        for x in 0..level.width {
+            if let Some(tile) = level.get_tile(x, y) {
+                // The exterior of the level stays blank
+                if *tile == TileType::Outside {
+                    continue;
+                }
+
+                let dest = Point2D {
+                    x: TILE_WIDTH * (x + offset.x),
+                    y: TILE_HEIGHT * (y + offset.y),
+                };
+
+                canvas.draw(
+                    bg_image,
+                    DrawParam::default()
+                        .dest(dest)
+                        .scale(get_scaling_factors(bg_image)),
+                );
+
+                if *tile == TileType::Wall || *tile == TileType::Target {
+                    let image = if *tile == TileType::Wall {
+                        sprites.get_sprite("wall").unwrap()
+                    } else {
+                        sprites.get_sprite("target").unwrap()
+                    };
+
+                    canvas.draw(
+                        image,
+                        DrawParam::default()
+                            .dest(Point2D {
+                                x: TILE_WIDTH * (x + offset.x),
+                                y: TILE_HEIGHT * (y + offset.y),
+                            })
+                            .scale(get_scaling_factors(image)),
+                    );
+                }
+            }
+        }
+    }
+}
+
+// Draws a miniature of the starting position of the level, centered in
+// `area`. Plain colored squares are used since the sprites become unreadable
+// at this size.
+pub fn draw_thumbnail(level: &Level, ctx: &Context, canvas: &mut Canvas, area: Rect) -> GameResult {
+    let size = (area.w / level.width as f32)
+        .min(area.h / level.height as f32)
+        .floor()
+        .max(1.0);
+    let left = area.x + (area.w - size * level.width as f32) / 2.0;
+    let top = area.y + (area.h - size * level.height as f32) / 2.0;
+    let square = |x: i32, y: i32, margin: f32| {
+        Rect::new(
+            left + x as f32 * size + margin,
+            top + y as f32 * size + margin,
+            size - 2.0 * margin,
+            size - 2.0 * margin,
+        )
+    };
+    let mut mb = MeshBuilder::new();
+
+    for y in 0..level.height {
+        for x in 0..level.width {
+            let color = match level.get_tile(x, y) {
+                Some(TileType::Wall) => Color::from_rgb(128, 128, 128),
+                Some(TileType::Floor) => Color::from_rgb(48, 48, 48),
+                Some(TileType::Target) => Color::from_rgb(160, 40, 40),
+                Some(TileType::Outside) | None => continue,
+            };
+
+            mb.rectangle(DrawMode::fill(), square(x, y, 0.0), color)?;
+        }
+    }
+
+    // Boxes and the player are drawn a bit smaller than a tile so that
+    // targets below them stay visible
+    let margin = (size / 6.0).floor();
+
+    for b in &level.boxes {
+        mb.rectangle(
+            DrawMode::fill(),
+            square(b.x, b.y, margin),
+            Color::from_rgb(200, 150, 60),
+        )?;
+    }
+
+    mb.rectangle(
+        DrawMode::fill(),
+        square(level.player.x, level.player.y, margin),
+        Color::from_rgb(60, 140, 220),
+    )?;
+
+    canvas.draw(&Mesh::from_data(ctx, mb.build()), DrawParam::default());
+
+    Ok(())
+}
+
+// Draws the player at `position`, in tiles, which lies between two tiles
+// while the player is shown walking.
+pub fn draw_player(
+    player: &Player,
+    sprites: &SpriteManager,
+    canvas: &mut Canvas,
+    offset: Point2D,
+    position: Point2<f32>,
+) {
+    let sprite_name = match player.direction() {
+        Direction::Up => "player_up",
+        Direction::Right => "player_right",
+        Direction::Down => "player_down",
+        Direction::Left => "player_left",
+    };
+    let sprite = sprites
+        .get_sprite(sprite_name)
+        .unwrap_or_else(|| panic!("Error getting sprite: {}", sprite_name));
+
+    let scale = get_scaling_factors(sprite);
+    let dest = Point2 {
+        x: TILE_WIDTH as f32 * (position.x + offset.x as f32),
+        y: TILE_HEIGHT as f32 * (position.y + offset.y as f32),
+    };
+
+    canvas.draw(sprite, DrawParam::default().scale(scale).dest(dest));
+}
+
+// Draws a box at `position`, in tiles, which lies between two tiles while
+// the box is shown sliding.
+pub fn draw_box(
+    sprites: &SpriteManager,
+    canvas: &mut Canvas,
+    offset: Point2D,
+    targets: &[Point2D],
+    deadlocked: bool,
+    position: Point2<f32>,
+) {
+    // Sliding onto a target lights the box up halfway there
+    let shown = Point2D {
+        x: position.x.round() as i32,
+        y: position.y.round() as i32,
+    };
+    let sprite = if targets.contains(&shown) {
+        sprites.get_sprite("box_on_target").unwrap()
+    } else {
+        sprites.get_sprite("box").unwrap()
+    };
+
+    let scale = get_scaling_factors(sprite);
+    let dest = Point2 {
+        x: TILE_WIDTH as f32 * (position.x + offset.x as f32),
+        y: TILE_HEIGHT as f32 * (position.y + offset.y as f32),
+    };
+
+    let mut param = DrawParam::default().scale(scale).dest(dest);
+
+    // Boxes that can't reach a target anymore are tinted red
+    if deadlocked {
+        param = param.color(Color::new(1.0, 0.35, 0.35, 1.0));
+    }
+
+    canvas.draw(sprite, param);
+}
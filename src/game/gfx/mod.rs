@@ -1,12 +1,23 @@
+pub mod animation;
+pub mod board;
 pub mod ui;
 
-use crate::prelude::*;
+use crate::gfx_prelude::*;
+use ggez::mint::Point2;
 use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
 
 pub struct SpriteManager {
     sprites: HashMap<String, Image>,
 }
 
+impl Default for SpriteManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SpriteManager {
     pub fn new() -> Self {
         Self {
@@ -34,3 +45,33 @@ impl SpriteManager {
         self.sprites.get(name)
     }
 }
+
+impl From<Point2D> for Point2<f32> {
+    fn from(p: Point2D) -> Self {
+        Point2 {
+            x: p.x as f32,
+            y: p.y as f32,
+        }
+    }
+}
+
+pub fn get_scaling_factors(image: &Image) -> [f32; 2] {
+    [
+        TILE_WIDTH as f32 / image.width() as f32,
+        TILE_HEIGHT as f32 / image.height() as f32,
+    ]
+}
+
+impl LevelManager {
+    // Loads a pack from the resource directories of ggez, e.g. "/levels.txt".
+    pub fn load_from_file(&mut self, ctx: &Context, path: &str) -> Result<(), LevelError> {
+        let mut contents = String::new();
+
+        ctx.fs
+            .open(path)
+            .map_err(|e| LevelError::Io(e.to_string()))?
+            .read_to_string(&mut contents)?;
+
+        self.load_from_str(&contents, collection_name(Path::new(path)))
+    }
+}
@@ -1,4 +1,4 @@
-use crate::prelude::*;
+use crate::gfx_prelude::*;
 
 // Wrapper around a TextFragment that sets a margin on the text
 pub struct TextBlock {
@@ -0,0 +1,75 @@
+use crate::prelude::*;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hint {
+    // Push the box on `from` one square in `direction`
+    Push { from: Point2D, direction: Direction },
+    // The position can't be solved anymore, but it could before the last
+    // `moves` moves
+    Undo { moves: usize },
+    Solved,
+    NotFound(SolverError),
+    // The search stopped without an answer, e.g. because it panicked
+    Failed,
+}
+
+// Asks the solver for the next push towards a solution from the current
+// position. If there is none, finds out how far back the last solvable
+// position is. Both share the time limit.
+pub fn find_hint(game: &Game, limits: &SolverLimits) -> Hint {
+    let started = Instant::now();
+
+    match solve(&game.to_level(), limits) {
+        Ok(solution) => next_push(game, &solution.lurd),
+        Err(SolverError::Unsolvable) => {
+            let mut earlier = game.clone();
+            let mut moves = 0;
+
+            while let Some(undone) = earlier.undo() {
+                moves += 1;
+
+                // Walking around can always be undone, so only positions
+                // before a push can make a difference
+                if undone.box_id.is_none() {
+                    continue;
+                }
+
+                let remaining = SolverLimits {
+                    time: limits.time.map(|t| {
+                        t.saturating_sub(started.elapsed())
+                            .max(Duration::from_millis(1))
+                    }),
+                    ..*limits
+                };
+
+                match solve(&earlier.to_level(), &remaining) {
+                    Ok(_) => return Hint::Undo { moves },
+                    Err(SolverError::Unsolvable) => (),
+                    Err(e) => return Hint::NotFound(e),
+                }
+            }
+
+            Hint::NotFound(SolverError::Unsolvable)
+        }
+        Err(e) => Hint::NotFound(e),
+    }
+}
+
+// The first push of a solution, after the walk leading up to it.
+fn next_push(game: &Game, solution: &str) -> Hint {
+    let mut player = game.player().position;
+
+    for step in parse_lurd(solution).unwrap_or_default() {
+        if step.push {
+            return Hint::Push {
+                from: player + step.direction.delta(),
+                direction: step.direction,
+            };
+        }
+
+        player += step.direction.delta();
+    }
+
+    Hint::Solved
+}
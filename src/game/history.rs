@@ -0,0 +1,265 @@
+use crate::prelude::*;
+
+// A move in the history tree. The root is a placeholder for the starting
+// position and holds no move of its own.
+#[derive(Clone, Debug)]
+struct Node {
+    player_move: PlayerMove,
+    parent: usize,
+    children: Vec<usize>,
+    // The child that redo continues with
+    selected: usize,
+    // Made in one go with the move before, so that undo and redo treat
+    // both as a single step
+    joined: bool,
+}
+
+// Every line of moves tried in a level. Undoing keeps the moves, and playing
+// something else afterwards starts a new branch next to the old one instead
+// of replacing it, so earlier attempts can always be redone.
+#[derive(Clone, Debug)]
+pub struct History {
+    nodes: Vec<Node>,
+    current: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node {
+                player_move: PlayerMove {
+                    delta: Point2D { x: 0, y: 0 },
+                    box_id: None,
+                },
+                parent: 0,
+                children: Vec::new(),
+                selected: 0,
+                joined: false,
+            }],
+            current: 0,
+        }
+    }
+
+    // Adds a move made from the current position, joined to the one before
+    // if it is part of the same batch. Repeating a move that was undone
+    // before follows its existing branch, as long as it is joined the same
+    // way. Otherwise it starts a branch of its own, so that undo and redo
+    // keep treating each branch the way it was played.
+    pub fn record(&mut self, player_move: PlayerMove, joined: bool) {
+        let current = self.current;
+        let existing = self.nodes[current].children.iter().position(|c| {
+            self.nodes[*c].player_move == player_move && self.nodes[*c].joined == joined
+        });
+
+        let selected = match existing {
+            Some(selected) => selected,
+            None => {
+                let idx = self.nodes.len();
+
+                self.nodes.push(Node {
+                    player_move,
+                    parent: current,
+                    children: Vec::new(),
+                    selected: 0,
+                    joined,
+                });
+                self.nodes[current].children.push(idx);
+
+                self.nodes[current].children.len() - 1
+            }
+        };
+
+        self.nodes[current].selected = selected;
+        self.current = self.nodes[current].children[selected];
+    }
+
+    // Steps back to the previous position and returns the move undone.
+    pub fn undo(&mut self) -> Option<PlayerMove> {
+        if self.current == 0 {
+            return None;
+        }
+
+        let node = &self.nodes[self.current];
+        let player_move = node.player_move;
+        let parent = node.parent;
+
+        // Redo should lead back to where we came from
+        self.nodes[parent].selected = self.nodes[parent]
+            .children
+            .iter()
+            .position(|c| *c == self.current)
+            .unwrap();
+        self.current = parent;
+
+        Some(player_move)
+    }
+
+    // Steps forward along the selected branch and returns the move redone.
+    pub fn redo(&mut self) -> Option<PlayerMove> {
+        let node = &self.nodes[self.current];
+        let next = *node.children.get(node.selected)?;
+
+        self.current = next;
+
+        Some(self.nodes[next].player_move)
+    }
+
+    // Whether the last move was made in one go with the one before it.
+    pub fn is_joined(&self) -> bool {
+        self.nodes[self.current].joined
+    }
+
+    // Whether the next move to redo was made in one go with the last one.
+    pub fn is_next_joined(&self) -> bool {
+        let node = &self.nodes[self.current];
+
+        node.children
+            .get(node.selected)
+            .is_some_and(|next| self.nodes[*next].joined)
+    }
+
+    // Selects the next of the branches that start at the current position,
+    // returning to the first one after the last.
+    pub fn next_branch(&mut self) {
+        let node = &mut self.nodes[self.current];
+
+        if !node.children.is_empty() {
+            node.selected = (node.selected + 1) % node.children.len();
+        }
+    }
+
+    // The selected branch (starting at 1) and the number of branches at the
+    // current position.
+    pub fn branches(&self) -> (usize, usize) {
+        let node = &self.nodes[self.current];
+
+        (node.selected + 1, node.children.len())
+    }
+
+    // Number of moves that can be redone along the selected branches.
+    pub fn redo_len(&self) -> usize {
+        let mut len = 0;
+        let mut node = &self.nodes[self.current];
+
+        while let Some(next) = node.children.get(node.selected) {
+            node = &self.nodes[*next];
+            len += 1;
+        }
+
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn walk(direction: Direction) -> PlayerMove {
+        PlayerMove {
+            delta: direction.delta(),
+            box_id: None,
+        }
+    }
+
+    #[test]
+    fn redo_follows_the_moves_undone() {
+        let mut history = History::new();
+
+        history.record(walk(Direction::Left), false);
+        history.record(walk(Direction::Up), false);
+
+        assert_eq!(history.undo(), Some(walk(Direction::Up)));
+        assert_eq!(history.undo(), Some(walk(Direction::Left)));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo_len(), 2);
+        assert_eq!(history.redo(), Some(walk(Direction::Left)));
+        assert_eq!(history.redo(), Some(walk(Direction::Up)));
+        assert_eq!(history.redo(), None);
+    }
+
+    #[test]
+    fn playing_on_after_undo_starts_a_branch() {
+        let mut history = History::new();
+
+        history.record(walk(Direction::Left), false);
+        history.record(walk(Direction::Up), false);
+        history.undo();
+        history.undo();
+        history.record(walk(Direction::Right), false);
+        history.undo();
+
+        // The new branch is selected, the old one is still there
+        assert_eq!(history.branches(), (2, 2));
+        assert_eq!(history.redo_len(), 1);
+
+        history.next_branch();
+
+        assert_eq!(history.branches(), (1, 2));
+        assert_eq!(history.redo_len(), 2);
+        assert_eq!(history.redo(), Some(walk(Direction::Left)));
+
+        // Repeating an undone move follows its branch instead of adding one
+        history.undo();
+        history.next_branch();
+        history.record(walk(Direction::Left), false);
+        history.undo();
+
+        assert_eq!(history.branches(), (1, 2));
+    }
+
+    #[test]
+    fn joined_moves_are_marked() {
+        let mut history = History::new();
+
+        history.record(walk(Direction::Left), false);
+        history.record(walk(Direction::Up), true);
+        history.record(walk(Direction::Up), true);
+
+        assert!(history.is_joined());
+
+        history.undo();
+        history.undo();
+
+        assert!(!history.is_joined());
+        assert!(history.is_next_joined());
+
+        history.undo();
+
+        assert!(!history.is_next_joined());
+    }
+
+    #[test]
+    fn moves_joined_differently_start_a_branch() {
+        let mut history = History::new();
+
+        history.record(walk(Direction::Left), false);
+        history.record(walk(Direction::Up), true);
+        history.undo();
+
+        // The same move, but made on its own
+        history.record(walk(Direction::Up), false);
+
+        assert!(!history.is_joined());
+
+        history.undo();
+
+        assert_eq!(history.branches(), (2, 2));
+        assert!(!history.is_next_joined());
+
+        history.next_branch();
+
+        assert!(history.is_next_joined());
+
+        // Repeating the batch follows its branch again
+        history.record(walk(Direction::Up), true);
+        history.undo();
+
+        assert_eq!(history.branches(), (1, 2));
+    }
+}
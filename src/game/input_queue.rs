@@ -0,0 +1,234 @@
+use crate::gfx_prelude::*;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_REPEAT_DELAY: Duration = Duration::from_millis(250);
+// Moves per second while an arrow key is held
+pub const DEFAULT_REPEAT_RATE: f32 = 12.0;
+// The limits accepted on the command line
+pub const MAX_REPEAT_DELAY: Duration = Duration::from_secs(10);
+pub const MAX_REPEAT_RATE: f32 = 100.0;
+// Keeps `update` from adding moves forever when given a silly rate
+const MIN_REPEAT_INTERVAL: Duration = Duration::from_millis(1);
+
+// How long an arrow key has to be held before it starts repeating, and the
+// time between repeats after that
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyRepeat {
+    pub delay: Duration,
+    // `None` turns key repeat off
+    pub interval: Option<Duration>,
+}
+
+impl Default for KeyRepeat {
+    fn default() -> Self {
+        Self::new(DEFAULT_REPEAT_DELAY, DEFAULT_REPEAT_RATE)
+    }
+}
+
+impl KeyRepeat {
+    // Key repeat given as the number of repeats per second, 0 turning it
+    // off. A rate so low that the interval can't be represented doesn't
+    // repeat either.
+    pub fn new(delay: Duration, rate: f32) -> Self {
+        let interval = if rate > 0.0 {
+            Duration::try_from_secs_f32(1.0 / rate)
+                .ok()
+                .map(|interval| interval.max(MIN_REPEAT_INTERVAL))
+        } else {
+            None
+        };
+
+        Self { delay, interval }
+    }
+}
+
+// Arrow key presses waiting to be played, in the order they were made.
+// Presses are taken from the key events rather than from the keyboard state
+// once per frame, so fast sequences within a single frame aren't lost. A
+// held arrow key adds more moves at the configured rate.
+#[derive(Debug, Default)]
+pub struct InputQueue {
+    repeat: KeyRepeat,
+    queue: VecDeque<Direction>,
+    // The arrow keys held down, the last one pressed at the end
+    held: Vec<Direction>,
+    // When the last key held adds its next move
+    next_repeat: Option<Instant>,
+}
+
+impl InputQueue {
+    pub fn new(repeat: KeyRepeat) -> Self {
+        Self {
+            repeat,
+            ..Self::default()
+        }
+    }
+
+    pub fn set_repeat(&mut self, repeat: KeyRepeat) {
+        self.repeat = repeat;
+    }
+
+    // The direction an arrow key moves in.
+    pub fn direction(keycode: KeyCode) -> Option<Direction> {
+        match keycode {
+            KeyCode::Up => Some(Direction::Up),
+            KeyCode::Right => Some(Direction::Right),
+            KeyCode::Down => Some(Direction::Down),
+            KeyCode::Left => Some(Direction::Left),
+            _ => None,
+        }
+    }
+
+    pub fn press(&mut self, direction: Direction, now: Instant) {
+        self.queue.push_back(direction);
+        self.held.retain(|d| *d != direction);
+        self.held.push(direction);
+        self.next_repeat = now.checked_add(self.repeat.delay);
+    }
+
+    // Releasing the last key pressed lets the one held before it repeat
+    // again, after the usual delay.
+    pub fn release(&mut self, direction: Direction, now: Instant) {
+        let was_last = self.held.last() == Some(&direction);
+
+        self.held.retain(|d| *d != direction);
+
+        if self.held.is_empty() {
+            self.next_repeat = None;
+        } else if was_last {
+            self.next_repeat = now.checked_add(self.repeat.delay);
+        }
+    }
+
+    // Adds the moves of the key held down that are due by `now`.
+    pub fn update(&mut self, now: Instant) {
+        let (Some(direction), Some(interval)) = (self.held.last(), self.repeat.interval) else {
+            return;
+        };
+
+        while let Some(next) = self.next_repeat.filter(|next| *next <= now) {
+            self.queue.push_back(*direction);
+            self.next_repeat = next.checked_add(interval);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Direction> {
+        self.queue.pop_front()
+    }
+
+    // Drops the waiting moves and forgets the keys held, e.g. when leaving
+    // the level.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.held.clear();
+        self.next_repeat = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repeat(delay: u64, rate: f32) -> KeyRepeat {
+        KeyRepeat::new(Duration::from_millis(delay), rate)
+    }
+
+    fn moves(queue: &mut InputQueue) -> Vec<Direction> {
+        std::iter::from_fn(|| queue.pop()).collect()
+    }
+
+    #[test]
+    fn keeps_presses_in_order() {
+        let mut queue = InputQueue::new(repeat(250, 10.0));
+        let start = Instant::now();
+
+        queue.press(Direction::Left, start);
+        queue.release(Direction::Left, start);
+        queue.press(Direction::Up, start);
+        queue.release(Direction::Up, start);
+        queue.update(start + Duration::from_secs(1));
+
+        assert_eq!(moves(&mut queue), [Direction::Left, Direction::Up]);
+    }
+
+    #[test]
+    fn repeats_held_keys_after_the_delay() {
+        let mut queue = InputQueue::new(repeat(250, 10.0));
+        let start = Instant::now();
+
+        queue.press(Direction::Right, start);
+        queue.update(start + Duration::from_millis(200));
+
+        assert_eq!(moves(&mut queue), [Direction::Right]);
+
+        // Repeats at 250, 350 and 450 milliseconds
+        queue.update(start + Duration::from_millis(480));
+
+        assert_eq!(moves(&mut queue), [Direction::Right; 3]);
+    }
+
+    #[test]
+    fn repeats_the_key_held_before_after_a_release() {
+        let mut queue = InputQueue::new(repeat(250, 10.0));
+        let start = Instant::now();
+
+        queue.press(Direction::Right, start);
+        queue.press(Direction::Down, start);
+        queue.release(Direction::Down, start + Duration::from_millis(100));
+        queue.update(start + Duration::from_millis(300));
+
+        assert_eq!(moves(&mut queue), [Direction::Right, Direction::Down]);
+
+        queue.update(start + Duration::from_millis(350));
+
+        assert_eq!(moves(&mut queue), [Direction::Right]);
+    }
+
+    #[test]
+    fn does_not_repeat_when_turned_off() {
+        let mut queue = InputQueue::new(repeat(0, 0.0));
+        let start = Instant::now();
+
+        queue.press(Direction::Up, start);
+        queue.update(start + Duration::from_secs(10));
+
+        assert_eq!(moves(&mut queue), [Direction::Up]);
+    }
+
+    #[test]
+    fn survives_extreme_settings() {
+        let start = Instant::now();
+
+        // Far too slow to ever repeat
+        assert_eq!(repeat(250, 1e-20).interval, None);
+
+        // Far too fast, limited to one move per interval
+        let mut queue = InputQueue::new(repeat(0, 1e12));
+
+        queue.press(Direction::Up, start);
+        queue.update(start + Duration::from_millis(10));
+
+        assert_eq!(moves(&mut queue).len(), 12);
+
+        // A delay that runs past the end of time
+        let mut queue = InputQueue::new(KeyRepeat::new(Duration::MAX, 10.0));
+
+        queue.press(Direction::Up, start);
+        queue.update(start + Duration::from_secs(10));
+
+        assert_eq!(moves(&mut queue), [Direction::Up]);
+    }
+
+    #[test]
+    fn clear_forgets_moves_and_held_keys() {
+        let mut queue = InputQueue::new(repeat(0, 10.0));
+        let start = Instant::now();
+
+        queue.press(Direction::Up, start);
+        queue.clear();
+        queue.update(start + Duration::from_secs(1));
+
+        assert_eq!(queue.pop(), None);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::prelude::*;
+
+// A named list of levels, usually everything that was read from one pack.
+#[derive(Clone, Debug)]
+pub struct Collection {
+    pub name: String,
+    pub author: Option<String>,
+    pub copyright: Option<String>,
+    pub id: Option<String>,
+    // Only changed through push_level, which keeps the fingerprints in step
+    levels: Vec<Level>,
+    // The fingerprint of every level, worked out once when it is added
+    fingerprints: Vec<String>,
+}
+
+impl Collection {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            author: None,
+            copyright: None,
+            id: None,
+            levels: Vec::new(),
+            fingerprints: Vec::new(),
+        }
+    }
+
+    pub fn num_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn get_level(&self, idx: usize) -> Option<&Level> {
+        self.levels.get(idx)
+    }
+
+    pub fn levels(&self) -> &[Level] {
+        &self.levels
+    }
+
+    pub fn push_level(&mut self, level: Level) {
+        self.fingerprints.push(level.fingerprint());
+        self.levels.push(level);
+    }
+
+    // Renames the collection along with the name its levels refer to.
+    pub fn set_name(&mut self, name: String) {
+        for level in &mut self.levels {
+            level.metadata.collection = Some(name.clone());
+        }
+
+        self.name = name;
+    }
+
+    pub fn get_fingerprint(&self, idx: usize) -> Option<&str> {
+        self.fingerprints.get(idx).map(String::as_str)
+    }
+
+    // Looks up a level by its number (starting at 1), its title or its id.
+    pub fn find_level(&self, name: &str) -> Option<usize> {
+        if let Ok(number) = name.parse::<usize>() {
+            if number >= 1 && number <= self.levels.len() {
+                return Some(number - 1);
+            }
+        }
+
+        self.levels.iter().position(|l| {
+            [&l.metadata.title, &l.metadata.id]
+                .into_iter()
+                .flatten()
+                .any(|t| t.eq_ignore_ascii_case(name))
+        })
+    }
+}
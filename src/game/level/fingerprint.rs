@@ -0,0 +1,212 @@
+use crate::prelude::*;
+use std::collections::HashMap;
+
+// Identifies a level by the layout of its board alone, so that progress,
+// solutions and statistics can refer to it no matter which pack or position
+// it comes from. Titles and other metadata are ignored, as are the empty
+// margins around the board and trailing whitespace. The result is a 64-bit
+// FNV-1a hash in hexadecimal, which stays the same across builds.
+impl Level {
+    pub fn fingerprint(&self) -> String {
+        hash_board(&normalize(self.board_rows()))
+    }
+
+    // Like `fingerprint`, but the same for all rotations and mirror images of
+    // the board. Useful to find levels that were published more than once.
+    pub fn symmetric_fingerprint(&self) -> String {
+        let rows = normalize(self.board_rows());
+        let mut grid: Vec<Vec<char>> = rows.iter().map(|r| r.chars().collect()).collect();
+        let mut canonical: Option<Vec<String>> = None;
+
+        for _ in 0..4 {
+            for board in [mirror(&grid), grid.clone()] {
+                let rows = normalize(board.iter().map(|r| r.iter().collect()).collect());
+
+                if canonical.as_ref().is_none_or(|c| rows < *c) {
+                    canonical = Some(rows);
+                }
+            }
+
+            grid = rotate(&grid);
+        }
+
+        hash_board(&canonical.unwrap_or_default())
+    }
+}
+
+impl LevelManager {
+    // Groups levels that share a layout, up to rotation and mirroring. Only
+    // groups of two or more are returned, each as collection and level
+    // indices in the order they were loaded.
+    pub fn find_duplicates(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut groups: Vec<Vec<(usize, usize)>> = Vec::new();
+        let mut group_of: HashMap<String, usize> = HashMap::new();
+
+        for collection in 0..self.num_collections() {
+            let levels = self.get_collection(collection).unwrap().levels();
+
+            for (idx, level) in levels.iter().enumerate() {
+                let group = *group_of
+                    .entry(level.symmetric_fingerprint())
+                    .or_insert_with(|| {
+                        groups.push(Vec::new());
+                        groups.len() - 1
+                    });
+
+                groups[group].push((collection, idx));
+            }
+        }
+
+        groups.retain(|group| group.len() > 1);
+
+        groups
+    }
+}
+
+// Strips trailing whitespace, empty rows at the top and bottom, and the
+// indentation shared by all rows.
+fn normalize(rows: Vec<String>) -> Vec<String> {
+    let mut rows: Vec<String> = rows.iter().map(|r| r.trim_end().to_owned()).collect();
+
+    while rows.last().is_some_and(|r| r.is_empty()) {
+        rows.pop();
+    }
+
+    let first = rows
+        .iter()
+        .position(|r| !r.is_empty())
+        .unwrap_or(rows.len());
+    let indent = rows
+        .iter()
+        .filter(|r| !r.is_empty())
+        .map(|r| r.len() - r.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    rows[first..]
+        .iter()
+        .map(|r| r.get(indent..).unwrap_or_default().to_owned())
+        .collect()
+}
+
+// Turns the board a quarter clockwise, padding short rows with floor.
+fn rotate(grid: &[Vec<char>]) -> Vec<Vec<char>> {
+    let width = grid.iter().map(Vec::len).max().unwrap_or(0);
+
+    (0..width)
+        .map(|x| {
+            grid.iter()
+                .rev()
+                .map(|row| row.get(x).copied().unwrap_or(' '))
+                .collect()
+        })
+        .collect()
+}
+
+fn mirror(grid: &[Vec<char>]) -> Vec<Vec<char>> {
+    let width = grid.iter().map(Vec::len).max().unwrap_or(0);
+
+    grid.iter()
+        .map(|row| {
+            (0..width)
+                .rev()
+                .map(|x| row.get(x).copied().unwrap_or(' '))
+                .collect()
+        })
+        .collect()
+}
+
+fn hash_board(rows: &[String]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for byte in rows.join("\n").bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The same board in all its rotations and mirror images
+    const BOARDS: [&str; 8] = [
+        "#####\n#@$.#\n#   #\n#####",
+        "#####\n#.$@#\n#   #\n#####",
+        "####\n# @#\n# $#\n# .#\n####",
+        "####\n#@ #\n#$ #\n#. #\n####",
+        "#####\n#   #\n#.$@#\n#####",
+        "#####\n#   #\n#@$.#\n#####",
+        "####\n#. #\n#$ #\n#@ #\n####",
+        "####\n# .#\n# $#\n# @#\n####",
+    ];
+
+    fn level(board: &str) -> Level {
+        let mut levels = LevelManager::new();
+
+        levels.load_from_str(board, None).unwrap();
+        levels.get_level(0, 0).unwrap().clone()
+    }
+
+    #[test]
+    fn ignores_indentation_and_empty_rows() {
+        let fingerprint = level(BOARDS[0]).fingerprint();
+
+        assert_eq!(
+            level("   #####\n   #@$.#\n   #   #\n   #####").fingerprint(),
+            fingerprint
+        );
+
+        // SLC rows can be empty, which XSB rows can't
+        let slc = "<SokobanLevels>
+  <LevelCollection>
+    <Level>
+      <L>#####</L>
+      <L>#@$.#</L>
+      <L>#   #</L>
+      <L>#####</L>
+      <L></L>
+      <L>   </L>
+    </Level>
+  </LevelCollection>
+</SokobanLevels>";
+
+        assert_eq!(level(slc).fingerprint(), fingerprint);
+        assert_ne!(level(BOARDS[1]).fingerprint(), fingerprint);
+    }
+
+    #[test]
+    fn rotations_and_mirror_images_share_a_symmetric_fingerprint() {
+        let fingerprint = level(BOARDS[0]).symmetric_fingerprint();
+
+        for board in BOARDS {
+            assert_eq!(
+                level(board).symmetric_fingerprint(),
+                fingerprint,
+                "{}",
+                board
+            );
+        }
+
+        assert_ne!(
+            level("######\n#@$ .#\n######").symmetric_fingerprint(),
+            fingerprint
+        );
+    }
+
+    #[test]
+    fn finds_duplicates_across_packs() {
+        let mut levels = LevelManager::new();
+
+        levels
+            .load_from_str(&format!("{}\n\n######\n#@$ .#\n######", BOARDS[0]), None)
+            .unwrap();
+        levels
+            .load_from_str(&format!("######\n#@ $.#\n######\n\n{}", BOARDS[5]), None)
+            .unwrap();
+
+        assert_eq!(levels.find_duplicates(), [vec![(0, 0), (1, 1)]]);
+    }
+}
@@ -0,0 +1,364 @@
+use std::fmt;
+
+// Everything that can go wrong while loading a level pack. Level indices
+// start at 0 and count the boards of a single file, lines and columns start
+// at 1 and refer to the file the level was read from. For run-length encoded
+// boards the column refers to the decoded row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LevelError {
+    Io(String),
+    NoLevels,
+    InFile {
+        path: String,
+        error: Box<LevelError>,
+    },
+    InvalidXml {
+        line: usize,
+        message: String,
+    },
+    UnknownCharacter {
+        level: usize,
+        line: usize,
+        column: usize,
+        character: char,
+    },
+    InvalidRle {
+        level: usize,
+        line: usize,
+        column: usize,
+    },
+    MissingPlayer {
+        level: usize,
+    },
+    MultiplePlayers {
+        level: usize,
+        line: usize,
+        column: usize,
+    },
+    NoBoxes {
+        level: usize,
+    },
+    BoxTargetMismatch {
+        level: usize,
+        boxes: usize,
+        targets: usize,
+    },
+    NotEnclosed {
+        level: usize,
+        line: usize,
+        column: usize,
+    },
+}
+
+impl LevelError {
+    // Errors found while reading a single board only know the position
+    // inside of it. This moves them to the level's place in its file, where
+    // `rows` holds the file line of every row of the board.
+    pub fn relocate(self, index: usize, rows: &[usize]) -> Self {
+        let file_line = |line: usize| rows.get(line - 1).copied().unwrap_or(line);
+
+        match self {
+            LevelError::Io(_)
+            | LevelError::NoLevels
+            | LevelError::InFile { .. }
+            | LevelError::InvalidXml { .. } => self,
+            LevelError::UnknownCharacter {
+                line,
+                column,
+                character,
+                ..
+            } => LevelError::UnknownCharacter {
+                level: index,
+                line: file_line(line),
+                column,
+                character,
+            },
+            LevelError::InvalidRle { line, column, .. } => LevelError::InvalidRle {
+                level: index,
+                line: file_line(line),
+                column,
+            },
+            LevelError::MissingPlayer { .. } => LevelError::MissingPlayer { level: index },
+            LevelError::MultiplePlayers { line, column, .. } => LevelError::MultiplePlayers {
+                level: index,
+                line: file_line(line),
+                column,
+            },
+            LevelError::NoBoxes { .. } => LevelError::NoBoxes { level: index },
+            LevelError::BoxTargetMismatch { boxes, targets, .. } => LevelError::BoxTargetMismatch {
+                level: index,
+                boxes,
+                targets,
+            },
+            LevelError::NotEnclosed { line, column, .. } => LevelError::NotEnclosed {
+                level: index,
+                line: file_line(line),
+                column,
+            },
+        }
+    }
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::Io(msg) => write!(f, "{}", msg),
+            LevelError::NoLevels => write!(f, "there are no levels"),
+            LevelError::InFile { path, error } => write!(f, "{}: {}", path, error),
+            LevelError::InvalidXml { line, message } => write!(f, "line {}: {}", line, message),
+            LevelError::UnknownCharacter {
+                level,
+                line,
+                column,
+                character,
+            } => write!(
+                f,
+                "level {}, line {}, column {}: unknown character '{}'",
+                level + 1,
+                line,
+                column,
+                character
+            ),
+            LevelError::InvalidRle {
+                level,
+                line,
+                column,
+            } => write!(
+                f,
+                "level {}, line {}, column {}: invalid run-length encoding",
+                level + 1,
+                line,
+                column
+            ),
+            LevelError::MissingPlayer { level } => {
+                write!(f, "level {}: there is no player", level + 1)
+            }
+            LevelError::MultiplePlayers {
+                level,
+                line,
+                column,
+            } => write!(
+                f,
+                "level {}, line {}, column {}: there is more than one player",
+                level + 1,
+                line,
+                column
+            ),
+            LevelError::NoBoxes { level } => write!(f, "level {}: there are no boxes", level + 1),
+            LevelError::BoxTargetMismatch {
+                level,
+                boxes,
+                targets,
+            } => write!(
+                f,
+                "level {}: {} boxes but {} targets",
+                level + 1,
+                boxes,
+                targets
+            ),
+            LevelError::NotEnclosed {
+                level,
+                line,
+                column,
+            } => write!(
+                f,
+                "level {}, line {}, column {}: the player can leave the level",
+                level + 1,
+                line,
+                column
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for LevelError {
+    fn from(e: std::io::Error) -> Self {
+        LevelError::Io(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use std::path::Path;
+    use std::time::Duration;
+
+    // A valid first level, so that errors are reported for the second one,
+    // which starts at line 9
+    const FIRST: &str = "Title: Pack
+
+; 1
+#####
+#@$.#
+#####
+
+; 2
+";
+
+    fn error(contents: &str) -> LevelError {
+        let mut levels = LevelManager::new();
+
+        levels.load_from_str(contents, None).unwrap_err()
+    }
+
+    fn second(board: &str) -> LevelError {
+        error(&format!("{}{}", FIRST, board))
+    }
+
+    #[test]
+    fn io_errors_name_the_file() {
+        let mut levels = LevelManager::new();
+        let error = levels
+            .load_from_path(Path::new("no/such/pack.txt"))
+            .unwrap_err();
+
+        match error {
+            LevelError::InFile { path, error } => {
+                assert_eq!(path, "no/such/pack.txt");
+                assert!(matches!(*error, LevelError::Io(_)));
+            }
+            _ => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[test]
+    fn reloading_an_empty_pack_finds_no_levels() {
+        let path = std::env::temp_dir().join(format!("rustoban-empty-{}.txt", std::process::id()));
+        let mut levels = LevelManager::new();
+
+        std::fs::write(&path, FIRST).unwrap();
+        levels.load_from_path(&path).unwrap();
+        levels.watch();
+
+        // Waits out the watch interval, which also makes sure the file gets
+        // a new modification time
+        std::thread::sleep(Duration::from_millis(600));
+        std::fs::write(&path, "").unwrap();
+
+        let result = levels.reload_if_changed();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result, Err(LevelError::NoLevels));
+        assert_eq!(levels.num_collections(), 1);
+    }
+
+    #[test]
+    fn invalid_xml() {
+        assert!(matches!(
+            error("<SokobanLevels>\n<LevelCollection>\n</SokobanLevels>"),
+            LevelError::InvalidXml { line: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn unknown_character() {
+        assert_eq!(
+            second("######\n#@$x.#\n######"),
+            LevelError::UnknownCharacter {
+                level: 1,
+                line: 10,
+                column: 4,
+                character: 'x',
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_rle() {
+        assert_eq!(
+            second("5#|#@$.#|5"),
+            LevelError::InvalidRle {
+                level: 1,
+                line: 9,
+                column: 10,
+            }
+        );
+    }
+
+    #[test]
+    fn missing_player() {
+        assert_eq!(
+            second("#####\n# $.#\n#####"),
+            LevelError::MissingPlayer { level: 1 }
+        );
+    }
+
+    #[test]
+    fn multiple_players() {
+        assert_eq!(
+            second("#####\n#@$.#\n#@  #\n#####"),
+            LevelError::MultiplePlayers {
+                level: 1,
+                line: 11,
+                column: 2,
+            }
+        );
+
+        // All rows of a run-length encoded board are on the same line
+        assert_eq!(
+            second("5#|#@$.#|#@--#|5#"),
+            LevelError::MultiplePlayers {
+                level: 1,
+                line: 9,
+                column: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn no_boxes() {
+        assert_eq!(
+            second("#####\n#@ .#\n#####"),
+            LevelError::NoBoxes { level: 1 }
+        );
+    }
+
+    #[test]
+    fn box_target_mismatch() {
+        assert_eq!(
+            second("######\n#@$$.#\n######"),
+            LevelError::BoxTargetMismatch {
+                level: 1,
+                boxes: 2,
+                targets: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn not_enclosed() {
+        assert_eq!(
+            second("#####\n @$.#\n#####"),
+            LevelError::NotEnclosed {
+                level: 1,
+                line: 10,
+                column: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn errors_in_slc_packs_point_to_the_row() {
+        let slc = "<SokobanLevels>
+  <LevelCollection>
+    <Level>
+      <L>#####</L>
+      <L>#@$x#</L>
+      <L>#####</L>
+    </Level>
+  </LevelCollection>
+</SokobanLevels>";
+
+        assert_eq!(
+            error(slc),
+            LevelError::UnknownCharacter {
+                level: 0,
+                line: 5,
+                column: 4,
+                character: 'x',
+            }
+        );
+    }
+}
@@ -1,126 +1,735 @@
 use crate::prelude::*;
-use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+// Every character that may appear in a board row of an XSB file. Besides the
+// classic notation this includes the '-' and '_' floor characters and the
+// 'p', 'P', 'b' and 'B' aliases some editors write.
+pub(super) const BOARD_CHARS: &str = " #@+$*.-_pPbB";
+
+// Additional characters of run-length encoded boards like "4#|#.@#".
+const RLE_CHARS: &str = "0123456789|()";
+
+// How often watched packs are checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+struct Watch {
+    last_check: Instant,
+    modified: Vec<Option<SystemTime>>,
+}
 
 #[derive(Debug)]
 pub struct LevelManager {
-    levels: Vec<Level>,
+    collections: Vec<Collection>,
+    // Everything passed to load_from_path, so the packs can be read again
+    sources: Vec<PathBuf>,
+    watch: Option<Watch>,
+}
+
+impl Default for LevelManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl LevelManager {
     pub fn new() -> Self {
-        Self { levels: Vec::new() }
+        Self {
+            collections: Vec::new(),
+            sources: Vec::new(),
+            watch: None,
+        }
     }
 
-    pub fn load_from_file(&mut self, ctx: &Context, path: &str) -> Result<(), String> {
-        match ctx.fs.open(path) {
-            Ok(mut file) => {
-                let mut contents = String::new();
+    // Loads a pack from anywhere on disk. Directories are searched for
+    // packs (but not recursively), which are loaded in alphabetical order.
+    pub fn load_from_path(&mut self, path: &Path) -> Result<(), LevelError> {
+        self.sources.push(path.to_path_buf());
+        self.read_path(path)
+    }
 
-                match file.read_to_string(&mut contents) {
-                    Ok(_) => {
-                        let levels: Vec<&str> = contents.split(';').collect();
+    fn read_path(&mut self, path: &Path) -> Result<(), LevelError> {
+        let in_file = |error: LevelError| LevelError::InFile {
+            path: path.display().to_string(),
+            error: Box::new(error),
+        };
 
-                        for level in levels {
-                            self.parse_level_string(level);
-                        }
+        if path.is_dir() {
+            for file in pack_files(path).map_err(|e| in_file(e.into()))? {
+                self.read_path(&file)?;
+            }
 
-                        Ok(())
-                    }
-                    Err(e) => Err(e.to_string()),
-                }
+            return Ok(());
+        }
+
+        let contents = std::fs::read_to_string(path).map_err(|e| in_file(e.into()))?;
+
+        self.load_from_str(&contents, collection_name(path))
+            .map_err(in_file)
+    }
+
+    pub fn load_from_str(
+        &mut self,
+        contents: &str,
+        collection: Option<String>,
+    ) -> Result<(), LevelError> {
+        // Editors on Windows like to start files with a byte order mark
+        let contents = contents.strip_prefix('\u{feff}').unwrap_or(contents);
+
+        // SLC collections are XML documents, everything else is read as XSB
+        let collections = if contents.trim_start().starts_with('<') {
+            parse_slc(contents, collection)?
+        } else {
+            vec![parse_xsb(contents, collection)?]
+        };
+
+        for collection in collections {
+            if collection.num_levels() > 0 {
+                self.collections.push(collection);
             }
-            Err(e) => Err(e.to_string()),
         }
+
+        Ok(())
     }
 
-    fn parse_level_string(&mut self, level_str: &str) {
-        let mut level = Level::new();
+    // Starts checking the packs loaded with load_from_path for changes, see
+    // reload_if_changed.
+    pub fn watch(&mut self) {
+        self.watch = Some(Watch {
+            last_check: Instant::now(),
+            modified: self.modification_times(),
+        });
+    }
 
-        // Because we know the level must be surrounded by walls every valid
-        // row must contain at least two wall tiles. We can filter out lines
-        // that don't contain any walls.
-        let lines: Vec<&str> = level_str
-            .split(char::is_control)
-            .filter(|l| l.contains('#'))
-            .collect();
+    // Reads all watched packs again if any of them changed since the last
+    // call. Returns whether the levels were replaced. If the changed packs
+    // can't be loaded, the current levels are kept.
+    pub fn reload_if_changed(&mut self) -> Result<bool, LevelError> {
+        match &self.watch {
+            Some(watch) if watch.last_check.elapsed() >= WATCH_INTERVAL => (),
+            _ => return Ok(false),
+        }
+
+        let modified = self.modification_times();
+        let watch = self.watch.as_mut().unwrap();
+
+        watch.last_check = Instant::now();
+
+        if watch.modified == modified {
+            return Ok(false);
+        }
+
+        // Remember the change even if loading fails, so a broken file is
+        // only reported once
+        watch.modified = modified;
+
+        let mut reloaded = LevelManager::new();
+
+        for source in &self.sources {
+            reloaded.read_path(source)?;
+        }
+
+        if reloaded.collections.is_empty() {
+            return Err(LevelError::NoLevels);
+        }
+
+        self.collections = reloaded.collections;
+
+        Ok(true)
+    }
+
+    // The modification time of every source and every pack inside of the
+    // directories among them.
+    fn modification_times(&self) -> Vec<Option<SystemTime>> {
+        let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+        let mut times = Vec::new();
+
+        for source in &self.sources {
+            times.push(modified(source));
+
+            if source.is_dir() {
+                let files = pack_files(source).unwrap_or_default();
+
+                times.extend(files.iter().map(|f| modified(f)));
+            }
+        }
+
+        times
+    }
+
+    pub fn num_collections(&self) -> usize {
+        self.collections.len()
+    }
+
+    pub fn get_collection(&self, idx: usize) -> Option<&Collection> {
+        self.collections.get(idx)
+    }
+
+    pub fn get_level(&self, collection: usize, level: usize) -> Option<&Level> {
+        self.get_collection(collection)?.get_level(level)
+    }
 
-        // A valid level consists of three or more rows.
-        if lines.len() < 3 {
-            return;
-        }
-
-        // Get the length of the longest row. This will be needed to determine
-        // how many spaces to insert at the end of every row to ensure they all
-        // have the same length.
-        let max_len = lines.iter().map(|l| l.len()).max().unwrap();
-
-        // Set the level dimensions.
-        level.width = max_len as i32;
-        level.height = lines.len() as i32;
-
-        for (row, l) in lines.iter().enumerate() {
-            let diff_len = max_len - l.len();
-
-            for (col, c) in l.char_indices() {
-                match c {
-                    '#' => level.tiles.push(TileType::Wall),
-                    ' ' => level.tiles.push(TileType::Floor),
-                    '@' => {
-                        level.player = Point2D {
-                            x: col as i32,
-                            y: row as i32,
-                        };
-                        level.tiles.push(TileType::Floor)
-                    }
-                    '+' => {
-                        let p = Point2D {
-                            x: col as i32,
-                            y: row as i32,
-                        };
-                        level.player = p;
-                        level.targets.push(p);
-                        level.tiles.push(TileType::Target)
-                    }
-                    '$' => {
-                        level.boxes.push(Point2D {
-                            x: col as i32,
-                            y: row as i32,
-                        });
-                        level.tiles.push(TileType::Floor)
-                    }
-                    '*' => {
-                        let p = Point2D {
-                            x: col as i32,
-                            y: row as i32,
-                        };
-                        level.boxes.push(p);
-                        level.targets.push(p);
-                        level.tiles.push(TileType::Target)
-                    }
-                    '.' => {
-                        level.targets.push(Point2D {
-                            x: col as i32,
-                            y: row as i32,
-                        });
-                        level.tiles.push(TileType::Target)
-                    }
-                    _ => (),
+    pub fn get_fingerprint(&self, collection: usize, level: usize) -> Option<&str> {
+        self.get_collection(collection)?.get_fingerprint(level)
+    }
+
+    // Finds a level by its number or title, looking through the collections
+    // in order. Returns the indices of the collection and the level.
+    pub fn find_level(&self, name: &str) -> Option<(usize, usize)> {
+        self.collections
+            .iter()
+            .enumerate()
+            .find_map(|(i, c)| c.find_level(name).map(|level| (i, level)))
+    }
+
+    // Finds the first level with the given fingerprint.
+    pub fn find_fingerprint(&self, fingerprint: &str) -> Option<(usize, usize)> {
+        self.collections.iter().enumerate().find_map(|(i, c)| {
+            (0..c.num_levels())
+                .position(|l| c.get_fingerprint(l) == Some(fingerprint))
+                .map(|level| (i, level))
+        })
+    }
+}
+
+// Packs are named after their file unless they contain a name of their own.
+pub(crate) fn collection_name(path: &Path) -> Option<String> {
+    path.file_stem().map(|s| s.to_string_lossy().into_owned())
+}
+
+// All packs inside of a directory in alphabetical order.
+fn pack_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && is_pack_file(p))
+        .collect();
+
+    files.sort();
+
+    Ok(files)
+}
+
+fn is_pack_file(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase());
+
+    matches!(
+        extension.as_deref(),
+        Some("txt") | Some("xsb") | Some("sok") | Some("slc")
+    )
+}
+
+// Reads a pack in the XSB format. Boards are recognized by their characters,
+// everything else is either a "Key: value" header or free text. Headers that
+// precede the first board describe the collection, headers that follow a
+// board belong to that board. The exception is "Title:", which names the
+// board right below it if there is no blank line in between, and only names
+// the collection if it is separated from the first board. The last line of
+// free text in front of a board (e.g. "; 1") is used as its title unless the
+// level has an explicit "Title:" header.
+fn parse_xsb(contents: &str, collection: Option<String>) -> Result<Collection, LevelError> {
+    let mut levels: Vec<Level> = Vec::new();
+    let mut header = LevelMetadata {
+        collection,
+        ..LevelMetadata::default()
+    };
+    let mut board: Vec<String> = Vec::new();
+    let mut board_rows: Vec<usize> = Vec::new();
+    let mut title: Option<String> = None;
+    // A "Title:" header that may still turn out to belong to the next board
+    let mut pending_title: Option<String> = None;
+    let mut comment: Option<Vec<&str>> = None;
+
+    for (line_no, line) in contents.lines().map(str::trim_end).enumerate() {
+        // Multi-line comments run until a "Comment-End:" line
+        if let Some(comment_lines) = comment.as_mut() {
+            let is_end = split_header(line)
+                .map(|(key, _)| key == "comment-end" || key == "comment_end")
+                .unwrap_or(false);
+
+            if is_end {
+                let meta = levels.last_mut().map_or(&mut header, |l| &mut l.metadata);
+
+                meta.comment = Some(comment_lines.join("\n"));
+                comment = None;
+            } else {
+                comment_lines.push(line);
+            }
+
+            continue;
+        }
+
+        if is_rle_board_line(line) {
+            let decoded = decode_rle(line).map_err(|e| e.relocate(levels.len(), &[line_no + 1]))?;
+
+            for row in decoded.lines() {
+                board.push(row.to_owned());
+                board_rows.push(line_no + 1);
+            }
+
+            continue;
+        }
+
+        if is_board_line(line) || (!board.is_empty() && is_damaged_board_line(line)) {
+            board.push(line.to_owned());
+            board_rows.push(line_no + 1);
+            continue;
+        }
+
+        if !board.is_empty() {
+            let title = pending_title.take().or(title.take());
+
+            push_board(&mut levels, &board, &board_rows, title, &header)?;
+            board.clear();
+            board_rows.clear();
+        }
+
+        if line.is_empty() {
+            set_title(&mut levels, &mut header, pending_title.take());
+            continue;
+        }
+
+        if let Some((key, value)) = split_header(line) {
+            let is_header = levels.is_empty();
+            let meta = levels.last_mut().map_or(&mut header, |l| &mut l.metadata);
+
+            match key.as_str() {
+                "title" => pending_title = Some(value.to_owned()),
+                "collection" => meta.collection = Some(value.to_owned()),
+                "author" => meta.author = Some(value.to_owned()),
+                "copyright" => meta.copyright = Some(value.to_owned()),
+                "id" if !is_header => meta.id = Some(value.to_owned()),
+                "comment" if value.is_empty() => comment = Some(Vec::new()),
+                "comment" => meta.comment = Some(value.to_owned()),
+                _ => (),
+            }
+        } else {
+            let text = line
+                .trim_start_matches(';')
+                .trim()
+                .trim_matches(|c| c == '\'' || c == '"');
+
+            if !text.is_empty() {
+                title = Some(text.to_owned());
+            }
+        }
+    }
+
+    if !board.is_empty() {
+        let title = pending_title.take().or(title);
+
+        push_board(&mut levels, &board, &board_rows, title, &header)?;
+    }
+
+    set_title(&mut levels, &mut header, pending_title);
+
+    let mut collection = Collection::new(header.collection.as_deref().unwrap_or("Untitled"));
+
+    collection.author = header.author;
+    collection.copyright = header.copyright;
+
+    for level in levels {
+        collection.push_level(level);
+    }
+
+    Ok(collection)
+}
+
+// Gives a "Title:" header that isn't followed by a board to the level before
+// it, or to the collection if there is none.
+fn set_title(levels: &mut [Level], header: &mut LevelMetadata, title: Option<String>) {
+    if title.is_none() {
+        return;
+    }
+
+    match levels.last_mut() {
+        Some(level) => level.metadata.title = title,
+        None => header.collection = title,
+    }
+}
+
+fn push_board(
+    levels: &mut Vec<Level>,
+    lines: &[String],
+    rows: &[usize],
+    title: Option<String>,
+    header: &LevelMetadata,
+) -> Result<(), LevelError> {
+    // Rows made of nothing but walls and floor are decoration, e.g. a line of
+    // '#' used as a separator between levels.
+    if lines.iter().all(|l| l.chars().all(|c| "#-_ ".contains(c))) {
+        return Ok(());
+    }
+
+    let index = levels.len();
+    let mut level = parse_board(lines)
+        .and_then(|level| level.validate().map(|_| level))
+        .map_err(|e| e.relocate(index, rows))?;
+
+    level.metadata = LevelMetadata {
+        title,
+        author: header.author.clone(),
+        copyright: header.copyright.clone(),
+        collection: header.collection.clone(),
+        ..LevelMetadata::default()
+    };
+
+    levels.push(level);
+
+    Ok(())
+}
+
+fn is_board_line(line: &str) -> bool {
+    // Every row of a closed board contains at least one wall, which also
+    // keeps lines consisting only of spaces from being taken for a board.
+    line.contains('#') && line.chars().all(|c| BOARD_CHARS.contains(c))
+}
+
+// Run-length encoded rows contain a count or a row separator somewhere, which
+// plain rows never do.
+fn is_rle_board_line(line: &str) -> bool {
+    line.contains('#')
+        && line.contains(|c: char| c.is_ascii_digit() || c == '|')
+        && line
+            .chars()
+            .all(|c| BOARD_CHARS.contains(c) || RLE_CHARS.contains(c))
+}
+
+// Inside a board, a row with a wall and a single character that doesn't
+// belong on a board is taken as a typo in the board, so that it is reported
+// instead of silently cutting the level in two. Text that merely mentions a
+// '#', such as "Level #5", has more than one such character.
+fn is_damaged_board_line(line: &str) -> bool {
+    let stray = line.chars().filter(|c| !BOARD_CHARS.contains(*c)).count();
+
+    line.contains('#') && stray == 1 && !line.trim_start().starts_with(';')
+}
+
+// Splits lines like "Author: David Skinner" into a lowercase key and its
+// value. Keys are single words so that titles such as "Level 1: Start" are
+// still treated as free text.
+fn split_header(line: &str) -> Option<(String, &str)> {
+    let (key, value) = line.split_once(':')?;
+    let key = key.trim();
+
+    if key.is_empty()
+        || !key.starts_with(|c: char| c.is_ascii_alphabetic())
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return None;
+    }
+
+    Some((key.to_ascii_lowercase(), value.trim()))
+}
+
+// Turns the rows of a board into a level. Positions in the returned error are
+// relative to the board.
+pub(super) fn parse_board(lines: &[String]) -> Result<Level, LevelError> {
+    let mut level = Level::new();
+    let mut player: Option<Point2D> = None;
+
+    // Get the length of the longest row. This will be needed to determine
+    // how many spaces to insert at the end of every row to ensure they all
+    // have the same length.
+    let max_len = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+    // Set the level dimensions.
+    level.width = max_len as i32;
+    level.height = lines.len() as i32;
+
+    for (row, l) in lines.iter().enumerate() {
+        let diff_len = max_len - l.chars().count();
+
+        for (col, c) in l.chars().enumerate() {
+            let p = Point2D {
+                x: col as i32,
+                y: row as i32,
+            };
+
+            if matches!(c, '@' | 'p' | '+' | 'P') {
+                if player.is_some() {
+                    return Err(LevelError::MultiplePlayers {
+                        level: 0,
+                        line: row + 1,
+                        column: col + 1,
+                    });
                 }
+
+                player = Some(p);
             }
 
-            for _ in 0..diff_len {
-                level.tiles.push(TileType::Floor);
+            match c {
+                '#' => level.tiles.push(TileType::Wall),
+                ' ' | '-' | '_' | '@' | 'p' => level.tiles.push(TileType::Floor),
+                '+' | 'P' => {
+                    level.targets.push(p);
+                    level.tiles.push(TileType::Target)
+                }
+                '$' | 'b' => {
+                    level.boxes.push(p);
+                    level.tiles.push(TileType::Floor)
+                }
+                '*' | 'B' => {
+                    level.boxes.push(p);
+                    level.targets.push(p);
+                    level.tiles.push(TileType::Target)
+                }
+                '.' => {
+                    level.targets.push(p);
+                    level.tiles.push(TileType::Target)
+                }
+                _ => {
+                    return Err(LevelError::UnknownCharacter {
+                        level: 0,
+                        line: row + 1,
+                        column: col + 1,
+                        character: c,
+                    })
+                }
             }
         }
 
-        self.levels.push(level);
+        for _ in 0..diff_len {
+            level.tiles.push(TileType::Floor);
+        }
     }
 
-    pub fn num_levels(&self) -> usize {
-        self.levels.len()
+    level.player = player.ok_or(LevelError::MissingPlayer { level: 0 })?;
+    level.mark_outside();
+
+    Ok(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACK: &str = "Title: Test pack
+Author: Someone
+
+; 1
+  ####
+###  #
+#@$. #
+#  *$##
+# .  #
+######
+Author: Someone else
+Comment: Three boxes
+
+#####
+#+$ #
+#####
+Title: Second
+Comment:
+Line one
+Line two
+Comment-End:
+";
+
+    // Starts with a byte order mark, as written by some editors
+    const SLC: &str = "\u{feff}<?xml version=\"1.0\" encoding=\"utf-8\"?>
+<SokobanLevels>
+  <Title>Tiny</Title>
+  <LevelCollection Copyright=\"Someone\">
+    <Level Id=\"First\" Width=\"5\" Height=\"3\">
+      <L>#####</L>
+      <L>#@$.#</L>
+      <L>#####</L>
+    </Level>
+    <Level Id=\"Second\" Width=\"5\" Height=\"3\" Copyright=\"Someone else\">
+      <L>#####</L>
+      <L>#+$ #</L>
+      <L>#####</L>
+    </Level>
+  </LevelCollection>
+</SokobanLevels>";
+
+    fn load(contents: &str) -> LevelManager {
+        let mut levels = LevelManager::new();
+
+        levels.load_from_str(contents, None).unwrap();
+
+        levels
+    }
+
+    fn levels(manager: &LevelManager) -> Vec<&Level> {
+        (0..manager.num_collections())
+            .flat_map(|c| manager.get_collection(c).unwrap().levels())
+            .collect()
     }
 
-    pub fn get_level(&self, idx: usize) -> Option<&Level> {
-        self.levels.get(idx)
+    #[test]
+    fn reads_titles_headers_and_comments() {
+        let manager = load(PACK);
+        let collection = manager.get_collection(0).unwrap();
+
+        assert_eq!(collection.name, "Test pack");
+        assert_eq!(collection.num_levels(), 2);
+
+        let first = &collection.levels()[0].metadata;
+
+        assert_eq!(first.title.as_deref(), Some("1"));
+        assert_eq!(first.author.as_deref(), Some("Someone else"));
+        assert_eq!(first.comment.as_deref(), Some("Three boxes"));
+
+        let second = &collection.levels()[1].metadata;
+
+        assert_eq!(second.title.as_deref(), Some("Second"));
+        assert_eq!(second.author.as_deref(), Some("Someone"));
+        assert_eq!(second.comment.as_deref(), Some("Line one\nLine two"));
+    }
+
+    #[test]
+    fn titles_above_boards_name_the_board() {
+        let manager = load(
+            "Title: Pack\n\nTitle: First\n#####\n#@$.#\n#####\nTitle: Second\n#####\n#@$.#\n#####",
+        );
+        let collection = manager.get_collection(0).unwrap();
+        let titles: Vec<Option<&str>> = collection
+            .levels()
+            .iter()
+            .map(|l| l.metadata.title.as_deref())
+            .collect();
+
+        assert_eq!(collection.name, "Pack");
+        assert_eq!(titles, [Some("First"), Some("Second")]);
+
+        // Without the blank line the pack has no name of its own
+        let manager = load("Title: First\n#####\n#@$.#\n#####");
+        let collection = manager.get_collection(0).unwrap();
+
+        assert_eq!(collection.name, "Untitled");
+        assert_eq!(
+            collection.levels()[0].metadata.title.as_deref(),
+            Some("First")
+        );
+    }
+
+    #[test]
+    fn tells_typos_from_text_after_a_board() {
+        let manager = load("#####\n#@$.#\n#####\nLevel #5 by me");
+
+        assert_eq!(levels(&manager).len(), 1);
+
+        let mut levels = LevelManager::new();
+
+        assert_eq!(
+            levels.load_from_str("#####\n#@$.#\n#x  #\n#####", None),
+            Err(LevelError::UnknownCharacter {
+                level: 0,
+                line: 3,
+                column: 2,
+                character: 'x',
+            })
+        );
+    }
+
+    #[test]
+    fn rle_round_trip() {
+        let manager = load(PACK);
+
+        for level in levels(&manager) {
+            let rle = level.to_rle();
+            let reloaded = load(&rle);
+            let reloaded = levels(&reloaded)[0];
+
+            assert_eq!(reloaded.to_rle(), rle);
+            assert_eq!(reloaded.tiles, level.tiles);
+            assert_eq!(reloaded.boxes, level.boxes);
+            assert_eq!(reloaded.targets, level.targets);
+            assert_eq!(reloaded.player, level.player);
+        }
+
+        assert_eq!(levels(&manager)[1].to_rle(), "5#|#+$-#|5#");
+    }
+
+    #[test]
+    fn reads_slc_collections() {
+        let manager = load(SLC);
+        let collection = manager.get_collection(0).unwrap();
+
+        assert_eq!(collection.name, "Tiny");
+        assert_eq!(collection.num_levels(), 2);
+
+        assert_eq!(collection.copyright.as_deref(), Some("Someone"));
+        assert_eq!(collection.find_level("second"), Some(1));
+
+        let first = &collection.levels()[0];
+
+        assert_eq!(first.metadata.id.as_deref(), Some("First"));
+        assert_eq!(first.metadata.copyright.as_deref(), Some("Someone"));
+        assert_eq!(first.metadata.author, None);
+        assert_eq!(first.player, Point2D { x: 1, y: 1 });
+        assert_eq!(first.boxes, [Point2D { x: 2, y: 1 }]);
+        assert_eq!(first.targets, [Point2D { x: 3, y: 1 }]);
+
+        let second = &collection.levels()[1];
+
+        assert_eq!(second.metadata.id.as_deref(), Some("Second"));
+        assert_eq!(second.metadata.copyright.as_deref(), Some("Someone else"));
+    }
+
+    #[test]
+    fn xsb_round_trip() {
+        let manager = load(PACK);
+
+        for level in levels(&manager) {
+            let xsb = level.to_xsb();
+            let reloaded = load(&xsb);
+
+            assert_eq!(levels(&reloaded)[0].to_xsb(), xsb);
+        }
+    }
+
+    #[test]
+    fn slc_round_trip() {
+        let manager = load(SLC);
+
+        for level in levels(&manager) {
+            let reloaded = load(&level.to_xsb());
+            let reloaded = levels(&reloaded)[0];
+
+            assert_eq!(reloaded.to_xsb(), level.to_xsb());
+            assert_eq!(reloaded.metadata.id, level.metadata.id);
+            assert_eq!(reloaded.metadata.copyright, level.metadata.copyright);
+        }
+    }
+
+    #[test]
+    fn reloads_watched_packs_when_they_change() {
+        let path = std::env::temp_dir().join(format!("rustoban-watch-{}.txt", std::process::id()));
+        let mut levels = LevelManager::new();
+
+        std::fs::write(&path, "#####\n#@$.#\n#####").unwrap();
+        levels.load_from_path(&path).unwrap();
+        levels.watch();
+
+        // Too soon to look again
+        assert_eq!(levels.reload_if_changed(), Ok(false));
+
+        std::thread::sleep(WATCH_INTERVAL + Duration::from_millis(100));
+
+        // Nothing changed
+        assert_eq!(levels.reload_if_changed(), Ok(false));
+
+        std::fs::write(&path, "#####\n#@$.#\n#####\n\n######\n#@$ .#\n######").unwrap();
+        std::thread::sleep(WATCH_INTERVAL + Duration::from_millis(100));
+
+        assert_eq!(levels.reload_if_changed(), Ok(true));
+        assert_eq!(levels.get_collection(0).unwrap().num_levels(), 2);
+
+        // Only reported once
+        std::thread::sleep(WATCH_INTERVAL + Duration::from_millis(100));
+
+        let reloaded = levels.reload_if_changed();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(reloaded, Ok(false));
     }
 }
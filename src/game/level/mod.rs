@@ -1,13 +1,51 @@
+mod collection;
+mod fingerprint;
+mod level_error;
 mod level_manager;
+mod rle;
+mod slc;
 
 use crate::prelude::*;
+pub use collection::*;
+pub use level_error::*;
 pub use level_manager::*;
+pub use rle::*;
+pub use slc::*;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TileType {
     Wall,
     Floor,
     Target,
+    // Floor that lies outside of the walls and can never be reached
+    Outside,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LevelMetadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub copyright: Option<String>,
+    pub comment: Option<String>,
+    pub collection: Option<String>,
+    // The identifier given to the level by its pack, e.g. "12" in SLC files
+    pub id: Option<String>,
+}
+
+impl LevelMetadata {
+    // The title of the level, or its id in packs that don't give titles.
+    pub fn name(&self) -> Option<&String> {
+        self.title.as_ref().or(self.id.as_ref())
+    }
+
+    // Name shown to the player, e.g. "levels - 12"
+    pub fn display_name(&self) -> String {
+        match (&self.collection, self.name()) {
+            (Some(collection), Some(title)) => format!("{} - {}", collection, title),
+            (Some(name), None) | (None, Some(name)) => name.clone(),
+            (None, None) => String::from("Untitled"),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -18,6 +56,13 @@ pub struct Level {
     pub player: Point2D,
     pub boxes: Vec<Point2D>,
     pub targets: Vec<Point2D>,
+    pub metadata: LevelMetadata,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Level {
@@ -29,6 +74,7 @@ impl Level {
             player: Point2D { x: 0, y: 0 },
             boxes: Vec::new(),
             targets: Vec::new(),
+            metadata: LevelMetadata::default(),
         }
     }
 
@@ -40,56 +86,228 @@ impl Level {
     }
 
     pub fn is_accessible(&self, point: Point2D) -> bool {
-        if let Some(tile) = self.get_tile(point.x, point.y) {
-            return *tile != TileType::Wall;
+        matches!(
+            self.get_tile(point.x, point.y),
+            Some(TileType::Floor) | Some(TileType::Target)
+        )
+    }
+
+    // Every tile that can be walked to from `start`, ignoring boxes, indexed
+    // like `tiles`.
+    pub fn reachable_area(&self, start: Point2D) -> Vec<bool> {
+        let mut visited = vec![false; self.tiles.len()];
+        let mut stack = vec![start];
+
+        while let Some(p) = stack.pop() {
+            let idx = (self.width * p.y + p.x) as usize;
+
+            if !self.is_accessible(p) || visited[idx] {
+                continue;
+            }
+
+            visited[idx] = true;
+
+            for direction in Direction::ALL {
+                stack.push(p + direction.delta());
+            }
         }
 
-        false
+        visited
     }
 
-    pub fn get_tile(&self, x: i32, y: i32) -> Option<&TileType> {
-        let idx = (self.width * y + x) as usize;
+    // Turns the floor around the walls into outside tiles, e.g. the space in
+    // front of indented rows. Floor enclosed by walls stays floor even if the
+    // player can't get to it, and so does the player's area, which is left
+    // for `validate` to check.
+    pub fn mark_outside(&mut self) {
+        let reachable = self.reachable_area(self.player);
+        let (width, height) = (self.width, self.height);
+        let mut stack: Vec<Point2D> = (0..width)
+            .flat_map(|x| [Point2D { x, y: 0 }, Point2D { x, y: height - 1 }])
+            .chain((0..height).flat_map(|y| [Point2D { x: 0, y }, Point2D { x: width - 1, y }]))
+            .collect();
 
-        self.tiles.get(idx)
+        while let Some(p) = stack.pop() {
+            if self.get_tile(p.x, p.y) != Some(&TileType::Floor) {
+                continue;
+            }
+
+            let idx = (width * p.y + p.x) as usize;
+
+            if reachable[idx] {
+                continue;
+            }
+
+            self.tiles[idx] = TileType::Outside;
+
+            for direction in Direction::ALL {
+                stack.push(p + direction.delta());
+            }
+        }
     }
 
-    pub fn draw(&self, sprites: &SpriteManager, canvas: &mut Canvas, offset: Point2D) {
-        let bg_image = sprites.get_sprite("floor").unwrap();
+    // Checks that the level can actually be played. The player's position
+    // is known to be unique at this point, so this looks at the boxes and at
+    // the walls around the area the player can walk in. Positions in the
+    // returned error are relative to the board.
+    pub fn validate(&self) -> Result<(), LevelError> {
+        if self.boxes.is_empty() {
+            return Err(LevelError::NoBoxes { level: 0 });
+        }
+
+        if self.boxes.len() != self.targets.len() {
+            return Err(LevelError::BoxTargetMismatch {
+                level: 0,
+                boxes: self.boxes.len(),
+                targets: self.targets.len(),
+            });
+        }
+
+        // Boxes are ignored as they might be pushed out of the way. If the
+        // area the player can walk in touches the edge of the board, the
+        // player could walk off of it.
+        let reachable = self.reachable_area(self.player);
 
         for y in 0..self.height {
             for x in 0..self.width {
-                if let Some(tile) = self.get_tile(x, y) {
-                    let dest = Point2D {
-                        x: TILE_WIDTH * (x + offset.x),
-                        y: TILE_HEIGHT * (y + offset.y),
-                    };
-
-                    canvas.draw(
-                        bg_image,
-                        DrawParam::default()
-                            .dest(dest)
-                            .scale(get_scaling_factors(bg_image)),
-                    );
-
-                    if *tile == TileType::Wall || *tile == TileType::Target {
-                        let image = if *tile == TileType::Wall {
-                            sprites.get_sprite("wall").unwrap()
-                        } else {
-                            sprites.get_sprite("target").unwrap()
-                        };
-
-                        canvas.draw(
-                            image,
-                            DrawParam::default()
-                                .dest(Point2D {
-                                    x: TILE_WIDTH * (x + offset.x),
-                                    y: TILE_HEIGHT * (y + offset.y),
-                                })
-                                .scale(get_scaling_factors(image)),
-                        );
-                    }
+                let is_edge = x == 0 || y == 0 || x == self.width - 1 || y == self.height - 1;
+
+                if is_edge && reachable[(self.width * y + x) as usize] {
+                    return Err(LevelError::NotEnclosed {
+                        level: 0,
+                        line: y as usize + 1,
+                        column: x as usize + 1,
+                    });
                 }
             }
         }
+
+        Ok(())
+    }
+
+    // Writes the level in XSB notation, followed by its title, id, author,
+    // copyright and comment headers. The result can be read back by the
+    // LevelManager.
+    pub fn to_xsb(&self) -> String {
+        let mut lines = self.board_rows();
+
+        if let Some(title) = &self.metadata.title {
+            lines.push(format!("Title: {}", title));
+        }
+
+        if let Some(id) = &self.metadata.id {
+            lines.push(format!("Id: {}", id));
+        }
+
+        if let Some(author) = &self.metadata.author {
+            lines.push(format!("Author: {}", author));
+        }
+
+        if let Some(copyright) = &self.metadata.copyright {
+            lines.push(format!("Copyright: {}", copyright));
+        }
+
+        if let Some(comment) = &self.metadata.comment {
+            if comment.contains('\n') {
+                lines.push(String::from("Comment:"));
+                lines.push(comment.clone());
+                lines.push(String::from("Comment-End:"));
+            } else {
+                lines.push(format!("Comment: {}", comment));
+            }
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    // Writes the starting position in compact run-length encoded form, with
+    // the rows separated by '|'.
+    pub fn to_rle(&self) -> String {
+        encode_rle(&self.board_rows().join("\n"))
+    }
+
+    // The board in XSB notation, one string per row without trailing floor.
+    fn board_rows(&self) -> Vec<String> {
+        let mut rows = Vec::new();
+
+        for y in 0..self.height {
+            let mut row = String::new();
+
+            for x in 0..self.width {
+                let p = Point2D { x, y };
+                let is_target = self.targets.contains(&p);
+                let c = if self.player == p {
+                    if is_target {
+                        '+'
+                    } else {
+                        '@'
+                    }
+                } else if self.boxes.contains(&p) {
+                    if is_target {
+                        '*'
+                    } else {
+                        '$'
+                    }
+                } else if is_target {
+                    '.'
+                } else if self.get_tile(x, y) == Some(&TileType::Wall) {
+                    '#'
+                } else {
+                    ' '
+                };
+
+                row.push(c);
+            }
+
+            rows.push(row.trim_end().to_owned());
+        }
+
+        rows
+    }
+
+    pub fn get_tile(&self, x: i32, y: i32) -> Option<&TileType> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let idx = (self.width * y + x) as usize;
+
+        self.tiles.get(idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_the_floor_around_the_walls_as_outside() {
+        // Indented and ragged rows, and a closed room above the player's
+        let mut levels = LevelManager::new();
+
+        levels
+            .load_from_str("  #####\n  # ###\n#######\n#@$.#\n#####", None)
+            .unwrap();
+
+        let level = levels.get_level(0, 0).unwrap();
+        let tile = |x: i32, y: i32| *level.get_tile(x, y).unwrap();
+
+        for (x, y) in [
+            (0, 0),
+            (1, 0),
+            (0, 1),
+            (1, 1),
+            (5, 3),
+            (6, 3),
+            (5, 4),
+            (6, 4),
+        ] {
+            assert_eq!(tile(x, y), TileType::Outside, "({}, {})", x, y);
+        }
+
+        assert_eq!(tile(3, 1), TileType::Floor);
+        assert_eq!(tile(1, 3), TileType::Floor);
+        assert_eq!(tile(2, 3), TileType::Floor);
+        assert_eq!(tile(3, 3), TileType::Target);
     }
 }
@@ -0,0 +1,114 @@
+use crate::prelude::*;
+
+// Longest text a run-length encoded string may expand to. Far more than any
+// board or solution needs, but small enough that a bogus count can't exhaust
+// memory.
+const MAX_DECODED_LEN: usize = 1 << 20;
+
+// Expands run-length encoded text such as "4#|#.@#" or "3l2U". A number
+// repeats the following character or parenthesized group, '|' separates rows
+// and becomes a line break. Positions in the returned error are relative to
+// the input, which counts as line 1.
+pub fn decode_rle(input: &str) -> Result<String, LevelError> {
+    let chars: Vec<char> = input.chars().collect();
+
+    decode_group(&chars, &mut 0, false)
+}
+
+fn decode_group(chars: &[char], pos: &mut usize, nested: bool) -> Result<String, LevelError> {
+    let error = |pos: usize| LevelError::InvalidRle {
+        level: 0,
+        line: 1,
+        column: pos + 1,
+    };
+    let mut decoded = String::new();
+
+    while *pos < chars.len() {
+        let start = *pos;
+        let mut count: Option<usize> = None;
+
+        while let Some(digit) = chars.get(*pos).and_then(|c| c.to_digit(10)) {
+            count = count
+                .unwrap_or(0)
+                .checked_mul(10)
+                .and_then(|c| c.checked_add(digit as usize))
+                .filter(|c| *c <= MAX_DECODED_LEN);
+
+            if count.is_none() {
+                return Err(error(start));
+            }
+
+            *pos += 1;
+        }
+
+        let part = match chars.get(*pos) {
+            Some('(') => {
+                *pos += 1;
+                decode_group(chars, pos, true)?
+            }
+            Some(')') if nested && count.is_none() => {
+                *pos += 1;
+                return Ok(decoded);
+            }
+            Some(')') | None => return Err(error(start)),
+            Some('|') => {
+                *pos += 1;
+                String::from("\n")
+            }
+            Some(c) => {
+                *pos += 1;
+                c.to_string()
+            }
+        };
+
+        let len = part
+            .len()
+            .checked_mul(count.unwrap_or(1))
+            .and_then(|len| len.checked_add(decoded.len()));
+
+        if len.is_none_or(|len| len > MAX_DECODED_LEN) {
+            return Err(error(start));
+        }
+
+        decoded.push_str(&part.repeat(count.unwrap_or(1)));
+    }
+
+    if nested {
+        // The closing parenthesis is missing
+        return Err(error(chars.len()));
+    }
+
+    Ok(decoded)
+}
+
+// Compresses every run of two or more equal characters into a count followed
+// by the character. Line breaks become '|', spaces become '-' so that the
+// result fits on a single line.
+pub fn encode_rle(input: &str) -> String {
+    let mut encoded = String::new();
+    let mut chars = input
+        .chars()
+        .map(|c| match c {
+            '\n' => '|',
+            ' ' => '-',
+            c => c,
+        })
+        .peekable();
+
+    while let Some(c) = chars.next() {
+        let mut count = 1;
+
+        while chars.peek() == Some(&c) {
+            chars.next();
+            count += 1;
+        }
+
+        if count > 1 {
+            encoded.push_str(&count.to_string());
+        }
+
+        encoded.push(c);
+    }
+
+    encoded
+}
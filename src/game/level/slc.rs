@@ -0,0 +1,334 @@
+use super::level_manager::{parse_board, BOARD_CHARS};
+use crate::prelude::*;
+
+// Reads a collection in the SLC format, the XML format used by Sokoban YASC
+// and others:
+//
+// <SokobanLevels>
+//   <Title>Microban</Title>
+//   <LevelCollection Copyright="David W. Skinner">
+//     <Level Id="1" Width="6" Height="7">
+//       <L>####</L>
+//       ...
+//
+// Every <LevelCollection> becomes a collection of its own, named after the
+// title of the file. Levels without a copyright of their own share the one of
+// their collection.
+pub fn parse_slc(
+    contents: &str,
+    collection: Option<String>,
+) -> Result<Vec<Collection>, LevelError> {
+    let mut reader = XmlReader::new(contents);
+    let mut collections: Vec<Collection> = Vec::new();
+    let mut title = collection;
+    let mut open_tags: Vec<String> = Vec::new();
+    let mut level: Option<LevelMetadata> = None;
+    let mut board: Vec<String> = Vec::new();
+    let mut board_rows: Vec<usize> = Vec::new();
+
+    while let Some((line, event)) = reader.next_event()? {
+        match event {
+            XmlEvent::Start {
+                name,
+                attributes,
+                is_empty,
+            } => {
+                let attribute = |key: &str| {
+                    attributes
+                        .iter()
+                        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                        .map(|(_, v)| v.clone())
+                };
+
+                match name.as_str() {
+                    "LevelCollection" => {
+                        let mut new_collection =
+                            Collection::new(title.as_deref().unwrap_or("Untitled"));
+
+                        new_collection.copyright = attribute("Copyright");
+                        new_collection.id = attribute("Id");
+                        collections.push(new_collection);
+                    }
+                    "Level" => {
+                        level = Some(LevelMetadata {
+                            copyright: attribute("Copyright")
+                                .or_else(|| collections.last().and_then(|c| c.copyright.clone())),
+                            collection: title.clone(),
+                            id: attribute("Id"),
+                            ..LevelMetadata::default()
+                        });
+                        board.clear();
+                        board_rows.clear();
+                    }
+                    // The row is filled in by the text that follows, if any
+                    "L" if level.is_some() => {
+                        board.push(String::new());
+                        board_rows.push(line);
+                    }
+                    _ => (),
+                }
+
+                if !is_empty {
+                    open_tags.push(name);
+                }
+            }
+            XmlEvent::End { name } => {
+                if open_tags.pop().as_deref() != Some(name.as_str()) {
+                    return Err(LevelError::InvalidXml {
+                        line,
+                        message: format!("unexpected closing tag </{}>", name),
+                    });
+                }
+
+                if name == "Level" {
+                    if let (Some(metadata), Some(collection)) =
+                        (level.take(), collections.last_mut())
+                    {
+                        let index = collection.num_levels();
+                        let mut new_level = parse_board(&board)
+                            .and_then(|l| l.validate().map(|_| l))
+                            .map_err(|e| e.relocate(index, &board_rows))?;
+
+                        new_level.metadata = metadata;
+                        collection.push_level(new_level);
+                    }
+                }
+            }
+            XmlEvent::Text { text } => match open_tags.last().map(String::as_str) {
+                Some("L") if level.is_some() => {
+                    // Spaces are floor, other whitespace such as line breaks
+                    // is just formatting
+                    let row = text
+                        .chars()
+                        .filter(|c| BOARD_CHARS.contains(*c) || !c.is_whitespace());
+
+                    if let Some(last) = board.last_mut() {
+                        last.extend(row);
+                    }
+                }
+                Some("Title") if open_tags.len() == 2 => {
+                    let text = text.trim();
+
+                    if !text.is_empty() {
+                        title = Some(text.to_owned());
+                    }
+                }
+                _ => (),
+            },
+        }
+    }
+
+    if let Some(name) = open_tags.pop() {
+        return Err(LevelError::InvalidXml {
+            line: reader.line,
+            message: format!("<{}> is never closed", name),
+        });
+    }
+
+    // Files with several collections share one title, so they are numbered
+    if collections.len() > 1 {
+        for (i, collection) in collections.iter_mut().enumerate() {
+            let name = format!("{} {}", collection.name, i + 1);
+
+            collection.set_name(name);
+        }
+    }
+
+    Ok(collections)
+}
+
+enum XmlEvent {
+    Start {
+        name: String,
+        attributes: Vec<(String, String)>,
+        is_empty: bool,
+    },
+    End {
+        name: String,
+    },
+    Text {
+        text: String,
+    },
+}
+
+// A small pull parser that understands just enough XML for level
+// collections: elements, attributes, text, comments and CDATA sections.
+struct XmlReader<'a> {
+    input: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> XmlReader<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn error(&self, message: &str) -> LevelError {
+        LevelError::InvalidXml {
+            line: self.line,
+            message: message.to_owned(),
+        }
+    }
+
+    // Moves past the next `len` bytes while keeping track of the line.
+    fn advance(&mut self, len: usize) -> &'a str {
+        let skipped = &self.input[self.pos..self.pos + len];
+
+        self.line += skipped.matches('\n').count();
+        self.pos += len;
+
+        skipped
+    }
+
+    // Returns everything up to `delimiter` and moves past the delimiter.
+    fn take_until(&mut self, delimiter: &str) -> Result<&'a str, LevelError> {
+        match self.input[self.pos..].find(delimiter) {
+            Some(len) => {
+                let taken = self.advance(len);
+
+                self.advance(delimiter.len());
+
+                Ok(taken)
+            }
+            None => Err(self.error(&format!("missing '{}'", delimiter))),
+        }
+    }
+
+    // Returns the next event together with the line it starts on.
+    fn next_event(&mut self) -> Result<Option<(usize, XmlEvent)>, LevelError> {
+        loop {
+            let rest = &self.input[self.pos..];
+            let line = self.line;
+
+            if rest.is_empty() {
+                return Ok(None);
+            }
+
+            if !rest.starts_with('<') {
+                let len = rest.find('<').unwrap_or(rest.len());
+                let text = unescape(self.advance(len));
+
+                if text.trim().is_empty() {
+                    continue;
+                }
+
+                return Ok(Some((line, XmlEvent::Text { text })));
+            }
+
+            if rest.starts_with("<?") {
+                self.take_until("?>")?;
+            } else if rest.starts_with("<!--") {
+                self.take_until("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.advance("<![CDATA[".len());
+
+                let text = self.take_until("]]>")?.to_owned();
+
+                return Ok(Some((line, XmlEvent::Text { text })));
+            } else if rest.starts_with("<!") {
+                self.take_until(">")?;
+            } else if rest.starts_with("</") {
+                self.advance(2);
+
+                let name = self.take_until(">")?.trim().to_owned();
+
+                return Ok(Some((line, XmlEvent::End { name })));
+            } else {
+                self.advance(1);
+
+                let tag = self.take_until(">")?;
+                let (tag, is_empty) = match tag.strip_suffix('/') {
+                    Some(tag) => (tag, true),
+                    None => (tag, false),
+                };
+                let (name, attributes) = self.parse_tag(tag)?;
+
+                return Ok(Some((
+                    line,
+                    XmlEvent::Start {
+                        name,
+                        attributes,
+                        is_empty,
+                    },
+                )));
+            }
+        }
+    }
+
+    fn parse_tag(&self, tag: &str) -> Result<(String, Vec<(String, String)>), LevelError> {
+        let tag = tag.trim();
+        let name_len = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let name = tag[..name_len].to_owned();
+        let mut rest = tag[name_len..].trim_start();
+        let mut attributes = Vec::new();
+
+        if name.is_empty() {
+            return Err(self.error("missing tag name"));
+        }
+
+        while !rest.is_empty() {
+            let (key, value) = rest
+                .split_once('=')
+                .ok_or_else(|| self.error(&format!("malformed attribute in <{}>", name)))?;
+            let value = value.trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|c| *c == '"' || *c == '\'')
+                .ok_or_else(|| self.error(&format!("unquoted attribute in <{}>", name)))?;
+            let end = value[1..]
+                .find(quote)
+                .ok_or_else(|| self.error(&format!("unterminated attribute in <{}>", name)))?;
+
+            attributes.push((key.trim().to_owned(), unescape(&value[1..end + 1])));
+            rest = value[end + 2..].trim_start();
+        }
+
+        Ok((name, attributes))
+    }
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest.find(';').map(|end| (&rest[1..end], end));
+        let decoded = entity.and_then(|(name, _)| match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => name
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| name.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        });
+
+        match (decoded, entity) {
+            (Some(c), Some((_, end))) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    unescaped.push_str(rest);
+
+    unescaped
+}
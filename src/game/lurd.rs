@@ -0,0 +1,311 @@
+use crate::prelude::*;
+
+// A single move in LURD notation: the direction the player walks in and
+// whether a box is pushed along the way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LurdStep {
+    pub direction: Direction,
+    pub push: bool,
+}
+
+impl LurdStep {
+    pub fn from_char(c: char) -> Option<Self> {
+        let direction = match c.to_ascii_lowercase() {
+            'l' => Direction::Left,
+            'u' => Direction::Up,
+            'r' => Direction::Right,
+            'd' => Direction::Down,
+            _ => return None,
+        };
+
+        Some(Self {
+            direction,
+            push: c.is_ascii_uppercase(),
+        })
+    }
+
+    pub fn to_char(&self) -> char {
+        let c = match self.direction {
+            Direction::Left => 'l',
+            Direction::Up => 'u',
+            Direction::Right => 'r',
+            Direction::Down => 'd',
+        };
+
+        if self.push {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        }
+    }
+}
+
+// Problems with a LURD string. Characters are counted from 1 in the string
+// as written, moves from 1 in the decoded solution.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LurdError {
+    UnknownCharacter { position: usize, character: char },
+    InvalidRle { position: usize },
+    IllegalMove { step: usize, error: MoveError },
+    MissingPush { step: usize },
+    UnexpectedPush { step: usize },
+    NotSolved { moves: usize },
+}
+
+impl std::fmt::Display for LurdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LurdError::UnknownCharacter {
+                position,
+                character,
+            } => write!(f, "character {}: unknown move '{}'", position, character),
+            LurdError::InvalidRle { position } => {
+                write!(f, "character {}: invalid run-length encoding", position)
+            }
+            LurdError::IllegalMove { step, error } => write!(f, "move {}: {}", step + 1, error),
+            LurdError::MissingPush { step } => {
+                write!(
+                    f,
+                    "move {}: a box is pushed, but the move is lowercase",
+                    step + 1
+                )
+            }
+            LurdError::UnexpectedPush { step } => {
+                write!(
+                    f,
+                    "move {}: no box is pushed, but the move is uppercase",
+                    step + 1
+                )
+            }
+            LurdError::NotSolved { moves } => {
+                write!(f, "the level is not solved after {} moves", moves)
+            }
+        }
+    }
+}
+
+// Reads a LURD string such as "ullDRdr". Whitespace is ignored and runs may
+// be written with counts, as in "3l2U".
+pub fn parse_lurd(input: &str) -> Result<Vec<LurdStep>, LurdError> {
+    let compact: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    // Maps positions in the compact string back to the input
+    let positions: Vec<usize> = input
+        .chars()
+        .enumerate()
+        .filter(|(_, c)| !c.is_whitespace())
+        .map(|(i, _)| i + 1)
+        .collect();
+    let position = |column: usize| {
+        positions
+            .get(column - 1)
+            .copied()
+            .unwrap_or(input.chars().count() + 1)
+    };
+
+    if let Some((column, character)) = compact.chars().enumerate().find(|(_, c)| {
+        LurdStep::from_char(*c).is_none() && !c.is_ascii_digit() && *c != '(' && *c != ')'
+    }) {
+        return Err(LurdError::UnknownCharacter {
+            position: position(column + 1),
+            character,
+        });
+    }
+
+    let decoded = decode_rle(&compact).map_err(|e| match e {
+        LevelError::InvalidRle { column, .. } => LurdError::InvalidRle {
+            position: position(column),
+        },
+        _ => LurdError::InvalidRle { position: 1 },
+    })?;
+
+    Ok(decoded.chars().filter_map(LurdStep::from_char).collect())
+}
+
+pub fn to_lurd(moves: &[PlayerMove]) -> String {
+    moves
+        .iter()
+        .filter_map(|m| {
+            Direction::from_delta(m.delta).map(|direction| LurdStep {
+                direction,
+                push: m.box_id.is_some(),
+            })
+        })
+        .map(|step| step.to_char())
+        .collect()
+}
+
+impl Game {
+    // The moves leading to the current position in LURD notation.
+    pub fn to_lurd(&self) -> String {
+        to_lurd(self.moves())
+    }
+
+    // Plays a move read from a LURD string, which must push a box exactly
+    // when it is uppercase. `step` is the index of the move, for errors.
+    pub fn apply_lurd(&mut self, step: usize, lurd: LurdStep) -> Result<MoveOutcome, LurdError> {
+        let destination = self.player().position + lurd.direction.delta();
+        let pushes = self
+            .boxes()
+            .iter()
+            .any(|b| *b.get_position() == destination);
+
+        // Walking into a wall is reported as such, whatever the case
+        if self.level().is_accessible(destination) {
+            if pushes && !lurd.push {
+                return Err(LurdError::MissingPush { step });
+            } else if !pushes && lurd.push {
+                return Err(LurdError::UnexpectedPush { step });
+            }
+        }
+
+        self.apply(lurd.direction)
+            .map_err(|error| LurdError::IllegalMove { step, error })
+    }
+}
+
+// Plays a whole LURD solution from the start of the level and checks that it
+// solves it. Returns the finished game.
+pub fn verify_solution(level: &Level, solution: &str) -> Result<Game, LurdError> {
+    let mut game = Game::new(level.clone());
+    let steps = parse_lurd(solution)?;
+
+    for (idx, step) in steps.iter().enumerate() {
+        game.apply_lurd(idx, *step)?;
+    }
+
+    if !game.is_solved() {
+        return Err(LurdError::NotSolved { moves: steps.len() });
+    }
+
+    Ok(game)
+}
+
+// A solution read from a solutions file, together with the line its header
+// is on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolutionEntry {
+    pub level: String,
+    pub line: usize,
+    pub solution: String,
+}
+
+// Reads a file of solutions as written by the game:
+//
+// ; Microban - 1
+// Level: 3f2a9c0b7d1e4a66
+// ullDRdr
+//
+// The level is named by its fingerprint, number or title, and the solution
+// may span several lines up to the next empty line. Lines starting with ';'
+// are comments.
+pub fn parse_solutions(contents: &str) -> Vec<SolutionEntry> {
+    let mut entries: Vec<SolutionEntry> = Vec::new();
+    let mut in_solution = false;
+
+    for (idx, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        if line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(level) = line.strip_prefix("Level:") {
+            entries.push(SolutionEntry {
+                level: level.trim().to_owned(),
+                line: idx + 1,
+                solution: String::new(),
+            });
+            in_solution = true;
+        } else if line.is_empty() {
+            in_solution = false;
+        } else if in_solution {
+            if let Some(entry) = entries.last_mut() {
+                entry.solution.push_str(line);
+            }
+        }
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(board: &str) -> Level {
+        let mut levels = LevelManager::new();
+
+        levels.load_from_str(board, None).unwrap();
+        levels.get_level(0, 0).unwrap().clone()
+    }
+
+    #[test]
+    fn reads_runs_and_groups() {
+        let steps = parse_lurd("2l U\n2(rD)").unwrap();
+        let lurd: String = steps.iter().map(LurdStep::to_char).collect();
+
+        assert_eq!(lurd, "llUrDrD");
+    }
+
+    #[test]
+    fn counts_characters_as_written() {
+        assert_eq!(
+            parse_lurd("ul x"),
+            Err(LurdError::UnknownCharacter {
+                position: 4,
+                character: 'x'
+            })
+        );
+        assert_eq!(
+            parse_lurd("ud)"),
+            Err(LurdError::InvalidRle { position: 3 })
+        );
+        // Counts too large to expand are reported where they start
+        assert_eq!(
+            parse_lurd("l 99999999999999999999r"),
+            Err(LurdError::InvalidRle { position: 3 })
+        );
+        // Unclosed groups are reported at the end
+        assert_eq!(
+            parse_lurd("ll 3(ud"),
+            Err(LurdError::InvalidRle { position: 8 })
+        );
+    }
+
+    #[test]
+    fn counts_moves_from_the_start() {
+        let pushing = level("#####\n#@$.#\n#####");
+        let walking = level("######\n#@ $.#\n######");
+
+        assert_eq!(
+            verify_solution(&pushing, "l").err(),
+            Some(LurdError::IllegalMove {
+                step: 0,
+                error: MoveError::Blocked
+            })
+        );
+        assert_eq!(
+            verify_solution(&pushing, "RR").err(),
+            Some(LurdError::IllegalMove {
+                step: 1,
+                error: MoveError::BoxBlocked
+            })
+        );
+        assert_eq!(
+            verify_solution(&pushing, "r").err(),
+            Some(LurdError::MissingPush { step: 0 })
+        );
+        assert_eq!(
+            verify_solution(&walking, "rR").ok().map(|g| g.to_lurd()),
+            Some(String::from("rR"))
+        );
+        assert_eq!(
+            verify_solution(&walking, "R").err(),
+            Some(LurdError::UnexpectedPush { step: 0 })
+        );
+        assert_eq!(
+            verify_solution(&walking, "r").err(),
+            Some(LurdError::NotSolved { moves: 1 })
+        );
+    }
+}
@@ -1,5 +1,18 @@
+#[cfg(feature = "gfx")]
 pub mod gfx;
+pub mod hint;
+pub mod history;
+#[cfg(feature = "gfx")]
+pub mod input_queue;
 pub mod level;
+pub mod lurd;
 pub mod movable_box;
+pub mod pathfinding;
 pub mod player;
+#[cfg(feature = "gfx")]
+pub mod progress;
+pub mod rules;
+pub mod solver;
+#[cfg(feature = "gfx")]
 pub mod state;
+pub mod statistics;
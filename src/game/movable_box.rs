@@ -36,26 +36,4 @@ impl MovableBox {
     pub fn is_on_target(&self, targets: &[Point2D]) -> bool {
         targets.contains(&self.position)
     }
-
-    pub fn draw(
-        &self,
-        sprites: &SpriteManager,
-        canvas: &mut Canvas,
-        offset: Point2D,
-        targets: &[Point2D],
-    ) {
-        let sprite = if self.is_on_target(targets) {
-            sprites.get_sprite("box_on_target").unwrap()
-        } else {
-            sprites.get_sprite("box").unwrap()
-        };
-
-        let scale = get_scaling_factors(sprite);
-        let dest = Point2D {
-            x: TILE_WIDTH * (self.position.x + offset.x),
-            y: TILE_HEIGHT * (self.position.y + offset.y),
-        };
-
-        canvas.draw(sprite, DrawParam::default().scale(scale).dest(dest));
-    }
 }
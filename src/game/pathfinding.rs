@@ -0,0 +1,425 @@
+use crate::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+// Where the box being pushed is, and which area around it the player is in,
+// as numbered by `Game::sides`
+type Placement = (Point2D, usize);
+
+// The placements a push search reached, each with the placement before it
+// and the direction of the push in between
+type Pushes = HashMap<Placement, (Placement, Direction)>;
+
+impl Game {
+    // The shortest way for the player to walk to `target` without pushing
+    // any boxes. Returns `None` if it can't be reached.
+    pub fn path_to(&self, target: Point2D) -> Option<Vec<Direction>> {
+        self.walk_path(self.player().position, target, |p| self.is_box(p))
+    }
+
+    // Walks the player to `target` along the shortest path. Every step is
+    // recorded as a regular move. Returns the number of steps taken, or
+    // `None` if the tile can't be reached.
+    pub fn walk_to(&mut self, target: Point2D) -> Option<usize> {
+        let path = self.path_to(target)?;
+
+        for direction in &path {
+            // The path only crosses free tiles, so no step can be blocked
+            self.apply(*direction).ok()?;
+        }
+
+        Some(path.len())
+    }
+
+    // The moves that push the box on `from` to `to` with as few pushes as
+    // possible, including the walks around it. All other boxes stay where
+    // they are. Returns `None` if the box can't get there that way.
+    pub fn push_path(&self, from: Point2D, to: Point2D) -> Option<Vec<Direction>> {
+        let (came_from, goal) = self.search_pushes(from, |p| p == to)?;
+        let mut pushes = Vec::new();
+        let mut current = goal?;
+
+        while let Some((previous, direction)) = came_from.get(&current) {
+            pushes.push((previous.0, *direction));
+            current = *previous;
+        }
+
+        // The search only knows which area the player is in, so the walks
+        // between the pushes are worked out now that the pushes are known
+        let mut path = Vec::new();
+        let mut player = self.player().position;
+
+        for (box_position, direction) in pushes.into_iter().rev() {
+            let walk = self.walk_path(player, box_position - direction.delta(), |p| {
+                p == box_position || (p != from && self.is_box(p))
+            })?;
+
+            path.extend(walk);
+            path.push(direction);
+            player = box_position;
+        }
+
+        Some(path)
+    }
+
+    // Every tile the box on `from` can be pushed to from the current
+    // position without moving any other box.
+    pub fn push_targets(&self, from: Point2D) -> Vec<Point2D> {
+        let Some((came_from, _)) = self.search_pushes(from, |_| false) else {
+            return Vec::new();
+        };
+        let mut targets: Vec<Point2D> = came_from
+            .keys()
+            .map(|(box_position, _)| *box_position)
+            .filter(|p| *p != from)
+            .collect();
+
+        targets.sort_by_key(|p| (p.y, p.x));
+        targets.dedup();
+
+        targets
+    }
+
+    // Every tile the player can walk to without pushing a box, indexed like
+    // the tiles of the level.
+    pub fn reachable_tiles(&self) -> Vec<bool> {
+        let level = self.level();
+        let mut reachable = vec![false; level.tiles.len()];
+        let mut stack = vec![self.player().position];
+
+        while let Some(p) = stack.pop() {
+            let idx = (level.width * p.y + p.x) as usize;
+
+            if !level.is_accessible(p) || self.is_box(p) || reachable[idx] {
+                continue;
+            }
+
+            reachable[idx] = true;
+
+            for direction in Direction::ALL {
+                stack.push(p + direction.delta());
+            }
+        }
+
+        reachable
+    }
+
+    // Pushes the box on `from` around, in order of the number of pushes,
+    // until it reaches a tile for which `is_goal` holds. Returns every
+    // placement reached with the one before it and the push in between,
+    // and the placement that reached the goal, if any.
+    fn search_pushes(
+        &self,
+        from: Point2D,
+        is_goal: impl Fn(Point2D) -> bool,
+    ) -> Option<(Pushes, Option<Placement>)> {
+        let pushed = self
+            .boxes()
+            .iter()
+            .position(|b| *b.get_position() == from)?;
+        let level = self.level();
+        // The tiles the player can never walk on while this box is pushed
+        let blocked: Vec<bool> = (0..level.tiles.len() as i32)
+            .map(|idx| {
+                let p = Point2D {
+                    x: idx % level.width,
+                    y: idx / level.width,
+                };
+
+                !level.is_accessible(p) || (p != from && self.is_box(p))
+            })
+            .collect();
+        // The sides of the box for every box position reached so far, so
+        // that the player's moves are only looked at once per box position
+        let mut sides: HashMap<Point2D, [Option<usize>; 4]> = HashMap::new();
+        let mut area_at = |box_position: Point2D, p: Point2D| {
+            let side = Direction::ALL
+                .iter()
+                .position(|d| box_position + d.delta() == p)?;
+
+            sides
+                .entry(box_position)
+                .or_insert_with(|| self.sides(&blocked, box_position))[side]
+        };
+        let player = self.player().position;
+        let mut came_from = Pushes::new();
+
+        // The player starts out in the area of the first side they can walk
+        // to. If there is none, the box can't be pushed at all.
+        let Some(area) = Direction::ALL
+            .iter()
+            .map(|d| from + d.delta())
+            .find(|side| {
+                self.walk_path(player, *side, |p| {
+                    p == from || blocked[(level.width * p.y + p.x) as usize]
+                })
+                .is_some()
+            })
+            .and_then(|side| area_at(from, side))
+        else {
+            return Some((came_from, is_goal(from).then_some((from, 0))));
+        };
+        let start = (from, area);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some((box_position, area)) = queue.pop_front() {
+            if is_goal(box_position) {
+                return Some((came_from, Some((box_position, area))));
+            }
+
+            // The boxes as they stand now, for checking where it can go
+            let mut boxes = self.boxes().to_vec();
+
+            *boxes[pushed].get_position_mut() = box_position;
+
+            for direction in Direction::ALL {
+                let delta = direction.delta();
+
+                // The player has to get behind the box to push it
+                if area_at(box_position, box_position - delta) != Some(area)
+                    || !boxes[pushed].can_be_moved(delta, level, &boxes)
+                {
+                    continue;
+                }
+
+                // After the push, the player stands where the box was
+                let Some(next_area) = area_at(box_position + delta, box_position) else {
+                    continue;
+                };
+                let next = (box_position + delta, next_area);
+
+                if next != start && !came_from.contains_key(&next) {
+                    came_from.insert(next, ((box_position, area), direction));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        Some((came_from, None))
+    }
+
+    // Works out which of the tiles next to the box on `box_position` the
+    // player can walk between, in the order of `Direction::ALL`. Each side
+    // is numbered after the first side in the same area, blocked sides get
+    // no number. The search stops as soon as every side is accounted for,
+    // which is usually right away.
+    fn sides(&self, blocked: &[bool], box_position: Point2D) -> [Option<usize>; 4] {
+        let level = self.level();
+        let index = |p: Point2D| (level.width * p.y + p.x) as usize;
+        let is_free =
+            |p: Point2D| level.is_accessible(p) && !blocked[index(p)] && p != box_position;
+        let tiles = Direction::ALL.map(|d| box_position + d.delta());
+        let mut sides: [Option<usize>; 4] = [None; 4];
+        // Shared by all searches, as they never enter each other's areas
+        let mut visited = vec![false; level.tiles.len()];
+
+        for side in 0..tiles.len() {
+            if sides[side].is_some() || !is_free(tiles[side]) {
+                continue;
+            }
+
+            sides[side] = Some(side);
+
+            let mut missing = (side + 1..tiles.len())
+                .filter(|other| is_free(tiles[*other]))
+                .count();
+            let mut queue = VecDeque::from([tiles[side]]);
+
+            visited[index(tiles[side])] = true;
+
+            while let Some(p) = queue.pop_front() {
+                if missing == 0 {
+                    break;
+                }
+
+                for direction in Direction::ALL {
+                    let next = p + direction.delta();
+
+                    if !is_free(next) || visited[index(next)] {
+                        continue;
+                    }
+
+                    visited[index(next)] = true;
+                    queue.push_back(next);
+
+                    if let Some(other) = tiles.iter().position(|t| *t == next) {
+                        sides[other] = Some(side);
+                        missing -= 1;
+                    }
+                }
+            }
+        }
+
+        sides
+    }
+
+    // Pushes the box on `from` to `to` as a single batch of moves, which
+    // undo takes back as a whole. Returns the number of moves made, or
+    // `None` if the box can't be pushed there.
+    pub fn push_box_to(&mut self, from: Point2D, to: Point2D) -> Option<usize> {
+        let path = self.push_path(from, to)?;
+
+        if path.is_empty() {
+            return Some(0);
+        }
+
+        self.apply_batch(&path).ok()?;
+
+        Some(path.len())
+    }
+
+    fn is_box(&self, p: Point2D) -> bool {
+        self.boxes().iter().any(|b| *b.get_position() == p)
+    }
+
+    // Breadth first search for the shortest walk between two tiles that
+    // avoids the tiles for which `is_blocked` holds.
+    fn walk_path(
+        &self,
+        start: Point2D,
+        target: Point2D,
+        is_blocked: impl Fn(Point2D) -> bool,
+    ) -> Option<Vec<Direction>> {
+        let level = self.level();
+        let index = |p: Point2D| (level.width * p.y + p.x) as usize;
+        let is_free = |p: Point2D| level.is_accessible(p) && !is_blocked(p);
+
+        if !is_free(target) {
+            return None;
+        }
+
+        // The direction each visited tile was entered from
+        let mut came_from: Vec<Option<Direction>> = vec![None; level.tiles.len()];
+        let mut visited = vec![false; level.tiles.len()];
+        let mut queue = VecDeque::from([start]);
+
+        visited[index(start)] = true;
+
+        while let Some(p) = queue.pop_front() {
+            if p == target {
+                let mut path = Vec::new();
+                let mut current = target;
+
+                while let Some(direction) = came_from[index(current)] {
+                    path.push(direction);
+                    current -= direction.delta();
+                }
+
+                path.reverse();
+
+                return Some(path);
+            }
+
+            for direction in Direction::ALL {
+                let next = p + direction.delta();
+
+                if is_free(next) && !visited[index(next)] {
+                    visited[index(next)] = true;
+                    came_from[index(next)] = Some(direction);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(board: &str) -> Game {
+        let mut levels = LevelManager::new();
+
+        levels.load_from_str(board, None).unwrap();
+
+        Game::new(levels.get_level(0, 0).unwrap().clone())
+    }
+
+    fn point(x: i32, y: i32) -> Point2D {
+        Point2D { x, y }
+    }
+
+    #[test]
+    fn walks_around_boxes() {
+        let mut game = game("######\n#@*  #\n# ** #\n#    #\n######");
+
+        assert_eq!(game.path_to(point(3, 1)).map(|path| path.len()), Some(8));
+        assert_eq!(game.walk_to(point(3, 1)), Some(8));
+        assert_eq!(game.player().position, point(3, 1));
+        assert_eq!(game.statistics().pushes, 0);
+    }
+
+    #[test]
+    fn does_not_walk_through_boxes() {
+        let game = game("######\n#@*  #\n######");
+
+        assert_eq!(game.path_to(point(2, 1)), None);
+        assert_eq!(game.path_to(point(3, 1)), None);
+    }
+
+    #[test]
+    fn numbers_the_sides_of_a_box_by_area() {
+        let sides = |board: &str, box_position: Point2D| {
+            let game = game(board);
+            let blocked = vec![false; game.level().tiles.len()];
+
+            game.sides(&blocked, box_position)
+        };
+
+        // In a corridor the player can't get from one side to the other
+        assert_eq!(
+            sides("######\n#@$ .#\n######", point(2, 1)),
+            [None, Some(1), None, Some(3)]
+        );
+        assert_eq!(
+            sides("#####\n#   #\n# $ #\n#@ .#\n#####", point(2, 2)),
+            [Some(0); 4]
+        );
+    }
+
+    #[test]
+    fn finds_the_tiles_a_box_can_be_pushed_to() {
+        let game = game("#######\n#@$   #\n#    .#\n#######");
+
+        // Along the wall only, there is no way to get above the box
+        assert_eq!(
+            game.push_targets(point(2, 1)),
+            [point(1, 1), point(3, 1), point(4, 1), point(5, 1)]
+        );
+        assert!(game.push_path(point(2, 1), point(4, 1)).is_some());
+        assert_eq!(game.push_path(point(2, 1), point(2, 2)), None);
+
+        // There is no box to push
+        assert!(game.push_targets(point(3, 1)).is_empty());
+    }
+
+    #[test]
+    fn pushes_boxes_around_other_boxes() {
+        let mut game = game(
+            "########\n\
+             #      #\n\
+             #      #\n\
+             #@$ *. #\n\
+             #      #\n\
+             #      #\n\
+             ########",
+        );
+        let (_, goal) = game
+            .search_pushes(point(2, 3), |p| p == point(5, 3))
+            .unwrap();
+
+        assert_eq!(goal.map(|(p, _)| p), Some(point(5, 3)));
+
+        game.push_box_to(point(2, 3), point(5, 3)).unwrap();
+
+        // Over a row next to the other box and back
+        assert_eq!(game.statistics().pushes, 5);
+        assert!(game.is_solved());
+        assert!(game.is_box(point(4, 3)));
+
+        // The whole walk is a single batch
+        assert!(game.undo_batch() > 5);
+        assert!(game.is_box(point(2, 3)));
+    }
+}
@@ -1,13 +1,44 @@
 use crate::prelude::*;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Direction {
+pub enum Direction {
     Up,
     Right,
     Down,
     Left,
 }
 
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+        Direction::Left,
+    ];
+
+    pub fn delta(&self) -> Point2D {
+        match self {
+            Direction::Up => Point2D { x: 0, y: -1 },
+            Direction::Right => Point2D { x: 1, y: 0 },
+            Direction::Down => Point2D { x: 0, y: 1 },
+            Direction::Left => Point2D { x: -1, y: 0 },
+        }
+    }
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Right => Direction::Left,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+        }
+    }
+
+    pub fn from_delta(delta: Point2D) -> Option<Direction> {
+        Direction::ALL.into_iter().find(|d| d.delta() == delta)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct PlayerMove {
     pub delta: Point2D,
@@ -28,32 +59,31 @@ impl Player {
         }
     }
 
+    // The direction the player last moved or tried to move in, which is
+    // where they face.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
     pub fn try_move(
         &mut self,
-        delta: Point2D,
+        direction: Direction,
         level: &Level,
-        boxes: &Vec<MovableBox>,
-    ) -> Result<PlayerMove, ()> {
+        boxes: &[MovableBox],
+    ) -> Result<PlayerMove, MoveError> {
+        let delta = direction.delta();
         let player_dest = self.position + delta;
 
-        if delta.x < 0 {
-            self.direction = Direction::Left;
-        } else if delta.x > 0 {
-            self.direction = Direction::Right;
-        } else if delta.y < 0 {
-            self.direction = Direction::Up;
-        } else if delta.y > 0 {
-            self.direction = Direction::Down;
-        }
+        self.direction = direction;
 
         if !level.is_accessible(player_dest) {
-            return Err(());
+            return Err(MoveError::Blocked);
         }
 
         for b in boxes {
             if *b.get_position() == player_dest {
                 if !b.can_be_moved(delta, level, boxes) {
-                    return Err(());
+                    return Err(MoveError::BoxBlocked);
                 }
 
                 self.position = player_dest;
@@ -72,24 +102,4 @@ impl Player {
             box_id: None,
         })
     }
-
-    pub fn draw(&self, sprites: &SpriteManager, canvas: &mut Canvas, offset: Point2D) {
-        let sprite_name = match self.direction {
-            Direction::Up => "player_up",
-            Direction::Right => "player_right",
-            Direction::Down => "player_down",
-            Direction::Left => "player_left",
-        };
-        let sprite = sprites
-            .get_sprite(sprite_name)
-            .expect(&format!("Error getting sprite: {}", sprite_name));
-
-        let scale = get_scaling_factors(sprite);
-        let dest = Point2D {
-            x: TILE_WIDTH * (self.position.x + offset.x),
-            y: TILE_HEIGHT * (self.position.y + offset.y),
-        };
-
-        canvas.draw(sprite, DrawParam::default().scale(scale).dest(dest));
-    }
 }
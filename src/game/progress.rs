@@ -0,0 +1,345 @@
+use crate::gfx_prelude::*;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+// Stored in the user data directory of the game
+const PROGRESS_FILE: &str = "/progress.txt";
+
+// The best results for a solved level: the best solution when ranked by
+// moves, the best one when ranked by pushes, and the fastest time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub by_moves: Statistics,
+    pub by_pushes: Statistics,
+    pub time: Duration,
+}
+
+impl Record {
+    pub fn new(stats: Statistics, time: Duration) -> Self {
+        Self {
+            by_moves: stats,
+            by_pushes: stats,
+            time,
+        }
+    }
+
+    pub fn best(&self, metric: Metric) -> &Statistics {
+        match metric {
+            Metric::Moves => &self.by_moves,
+            Metric::Pushes => &self.by_pushes,
+        }
+    }
+
+    // Keeps the better result for every ranking. Returns whether anything
+    // improved.
+    pub fn merge(&mut self, other: Record) -> bool {
+        let before = *self;
+
+        if other.by_moves.is_better_than(&self.by_moves, Metric::Moves) {
+            self.by_moves = other.by_moves;
+        }
+
+        if other
+            .by_pushes
+            .is_better_than(&self.by_pushes, Metric::Pushes)
+        {
+            self.by_pushes = other.by_pushes;
+        }
+
+        self.time = self.time.min(other.time);
+
+        *self != before
+    }
+}
+
+// Everything the player achieved so far. Levels are referred to by their
+// fingerprint rather than by index, so that reordering or extending a pack
+// keeps the progress intact.
+#[derive(Debug, Default)]
+pub struct Progress {
+    records: HashMap<String, Record>,
+    last_level: Option<String>,
+    // How solutions are ranked in the level overview
+    metric: Option<Metric>,
+    // Whether pushes into a deadlock are refused
+    safe_mode: bool,
+    changed: bool,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Reads the progress file. A missing file means nothing has been played
+    // yet.
+    pub fn load(ctx: &Context) -> Result<Self, String> {
+        if !ctx.fs.is_file(PROGRESS_FILE) {
+            return Ok(Self::new());
+        }
+
+        let mut contents = String::new();
+
+        ctx.fs
+            .open(PROGRESS_FILE)
+            .map_err(|e| e.to_string())?
+            .read_to_string(&mut contents)
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self::parse(&contents))
+    }
+
+    // Writes the progress file if anything changed since it was last saved.
+    pub fn save(&mut self, ctx: &Context) -> GameResult {
+        if !self.changed {
+            return Ok(());
+        }
+
+        // A failed save is reported once rather than retried on every frame
+        self.changed = false;
+
+        let mut file = ctx.fs.create(PROGRESS_FILE)?;
+
+        file.write_all(self.to_text().as_bytes())?;
+
+        Ok(())
+    }
+
+    // The file holds one entry per line:
+    //
+    // last 3f2a9c0b7d1e4a66
+    // rank pushes
+    // safe-mode on
+    // solved 3f2a9c0b7d1e4a66 by-moves=97,23,4,31 by-pushes=103,21,3,35 time=41.250
+    //
+    // Statistics list moves, pushes, box changes and player lines. Lines that
+    // can't be understood are skipped.
+    pub fn parse(contents: &str) -> Self {
+        let mut progress = Self::new();
+
+        for line in contents.lines() {
+            let mut words = line.split_whitespace();
+
+            match (words.next(), words.next()) {
+                (Some("last"), Some(fingerprint)) => {
+                    progress.last_level = Some(fingerprint.to_owned());
+                }
+                (Some("rank"), Some("moves")) => progress.metric = Some(Metric::Moves),
+                (Some("rank"), Some("pushes")) => progress.metric = Some(Metric::Pushes),
+                (Some("safe-mode"), Some(value)) => progress.safe_mode = value == "on",
+                (Some("solved"), Some(fingerprint)) => {
+                    if let Some(record) = parse_record(words) {
+                        progress.records.insert(fingerprint.to_owned(), record);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        progress
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![String::from("; Rustoban progress")];
+        let mut fingerprints: Vec<&String> = self.records.keys().collect();
+
+        if let Some(fingerprint) = &self.last_level {
+            lines.push(format!("last {}", fingerprint));
+        }
+
+        if let Some(metric) = self.metric {
+            lines.push(format!("rank {}", metric.name()));
+        }
+
+        if self.safe_mode {
+            lines.push(String::from("safe-mode on"));
+        }
+
+        // Sorted so that the file doesn't change needlessly between saves
+        fingerprints.sort();
+
+        for fingerprint in fingerprints {
+            let record = &self.records[fingerprint];
+
+            lines.push(format!(
+                "solved {} by-moves={} by-pushes={} time={:.3}",
+                fingerprint,
+                format_statistics(&record.by_moves),
+                format_statistics(&record.by_pushes),
+                record.time.as_secs_f64()
+            ));
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    pub fn get_record(&self, fingerprint: &str) -> Option<&Record> {
+        self.records.get(fingerprint)
+    }
+
+    pub fn is_solved(&self, fingerprint: &str) -> bool {
+        self.records.contains_key(fingerprint)
+    }
+
+    // Adds the result of solving a level. Returns whether it set a new best
+    // for any of the statistics.
+    pub fn add_record(&mut self, fingerprint: &str, record: Record) -> bool {
+        let improved = match self.records.get_mut(fingerprint) {
+            Some(best) => best.merge(record),
+            None => {
+                self.records.insert(fingerprint.to_owned(), record);
+                true
+            }
+        };
+
+        self.changed |= improved;
+
+        improved
+    }
+
+    pub fn last_level(&self) -> Option<&str> {
+        self.last_level.as_deref()
+    }
+
+    pub fn set_last_level(&mut self, fingerprint: String) {
+        if self.last_level.as_ref() != Some(&fingerprint) {
+            self.last_level = Some(fingerprint);
+            self.changed = true;
+        }
+    }
+
+    pub fn metric(&self) -> Metric {
+        self.metric.unwrap_or(Metric::Moves)
+    }
+
+    pub fn set_metric(&mut self, metric: Metric) {
+        self.metric = Some(metric);
+        self.changed = true;
+    }
+
+    pub fn safe_mode(&self) -> bool {
+        self.safe_mode
+    }
+
+    pub fn set_safe_mode(&mut self, safe_mode: bool) {
+        self.safe_mode = safe_mode;
+        self.changed = true;
+    }
+}
+
+fn format_statistics(stats: &Statistics) -> String {
+    format!(
+        "{},{},{},{}",
+        stats.moves, stats.pushes, stats.box_changes, stats.player_lines
+    )
+}
+
+fn parse_statistics(value: &str) -> Option<Statistics> {
+    let counts: Vec<usize> = value
+        .split(',')
+        .map(|c| c.parse().ok())
+        .collect::<Option<_>>()?;
+
+    match counts[..] {
+        [moves, pushes, box_changes, player_lines] => Some(Statistics {
+            moves,
+            pushes,
+            box_changes,
+            player_lines,
+        }),
+        _ => None,
+    }
+}
+
+fn parse_record<'a>(fields: impl Iterator<Item = &'a str>) -> Option<Record> {
+    let mut by_moves = None;
+    let mut by_pushes = None;
+    let mut time = None;
+
+    for field in fields {
+        match field.split_once('=')? {
+            ("by-moves", value) => by_moves = parse_statistics(value),
+            ("by-pushes", value) => by_pushes = parse_statistics(value),
+            ("time", value) => {
+                time = value
+                    .parse()
+                    .ok()
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            }
+            _ => (),
+        }
+    }
+
+    Some(Record {
+        by_moves: by_moves?,
+        by_pushes: by_pushes?,
+        time: time?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "; Rustoban progress
+last 3f2a9c0b7d1e4a66
+rank pushes
+safe-mode on
+solved 0a1b2c3d4e5f6789 by-moves=12,3,1,4 by-pushes=12,3,1,4 time=5.000
+solved 3f2a9c0b7d1e4a66 by-moves=97,23,4,31 by-pushes=103,21,3,35 time=41.250
+";
+
+    fn stats(moves: usize, pushes: usize) -> Statistics {
+        Statistics {
+            moves,
+            pushes,
+            box_changes: 1,
+            player_lines: 1,
+        }
+    }
+
+    #[test]
+    fn parse_and_to_text_round_trip() {
+        let progress = Progress::parse(TEXT);
+
+        assert_eq!(progress.last_level(), Some("3f2a9c0b7d1e4a66"));
+        assert_eq!(progress.metric(), Metric::Pushes);
+        assert!(progress.safe_mode());
+
+        let record = progress.get_record("3f2a9c0b7d1e4a66").unwrap();
+
+        assert_eq!(record.by_moves.moves, 97);
+        assert_eq!(record.by_pushes.pushes, 21);
+        assert_eq!(record.time, Duration::from_millis(41_250));
+        assert_eq!(progress.to_text(), TEXT);
+    }
+
+    #[test]
+    fn skips_lines_it_does_not_understand() {
+        let progress = Progress::parse("solved abc by-moves=1,2 time=1\nsomething else\nrank 3");
+
+        assert!(!progress.is_solved("abc"));
+        assert_eq!(progress.metric(), Metric::Moves);
+    }
+
+    #[test]
+    fn merge_keeps_the_best_moves_and_pushes_separately() {
+        let mut record = Record::new(stats(20, 10), Duration::from_secs(30));
+
+        // Fewer moves but more pushes, and slower
+        assert!(record.merge(Record::new(stats(18, 12), Duration::from_secs(40))));
+        assert_eq!(record.by_moves, stats(18, 12));
+        assert_eq!(record.by_pushes, stats(20, 10));
+        assert_eq!(record.time, Duration::from_secs(30));
+
+        // Fewer pushes but more moves, and faster
+        assert!(record.merge(Record::new(stats(25, 8), Duration::from_secs(20))));
+        assert_eq!(record.by_moves, stats(18, 12));
+        assert_eq!(record.by_pushes, stats(25, 8));
+        assert_eq!(record.time, Duration::from_secs(20));
+
+        // Nothing better
+        assert!(!record.merge(Record::new(stats(30, 15), Duration::from_secs(60))));
+    }
+}
@@ -0,0 +1,395 @@
+use crate::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    // The player would walk into a wall or off the board
+    Blocked,
+    // The box in front of the player can't be pushed any further
+    BoxBlocked,
+    // The push would make the level unsolvable, which safe mode prevents
+    Deadlock,
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MoveError::Blocked => write!(f, "the way is blocked"),
+            MoveError::BoxBlocked => write!(f, "the box can't be pushed"),
+            MoveError::Deadlock => write!(f, "the push would make the level unsolvable"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveOutcome {
+    pub player_move: PlayerMove,
+    pub solved: bool,
+}
+
+// Headless model of a single level being played. It knows nothing about
+// windows, input or rendering, so it can be driven by the game as well as by
+// tools that only need the rules.
+#[derive(Clone, Debug)]
+pub struct Game {
+    level: Level,
+    player: Player,
+    boxes: Vec<MovableBox>,
+    // The moves leading to the current position
+    moves: Vec<PlayerMove>,
+    history: History,
+    // Knows the dead squares of the level
+    board: Board,
+    safe_mode: bool,
+}
+
+impl Game {
+    pub fn new(level: Level) -> Self {
+        let player = Player::new(level.player);
+        let boxes = level
+            .boxes
+            .iter()
+            .map(|pos| MovableBox::new(*pos))
+            .collect();
+
+        Self {
+            player,
+            boxes,
+            moves: Vec::new(),
+            history: History::new(),
+            board: Board::new(&level),
+            level,
+            safe_mode: false,
+        }
+    }
+
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+
+    pub fn boxes(&self) -> &[MovableBox] {
+        &self.boxes
+    }
+
+    pub fn moves(&self) -> &[PlayerMove] {
+        &self.moves
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        Statistics::from_moves(&self.moves)
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn safe_mode(&self) -> bool {
+        self.safe_mode
+    }
+
+    // In safe mode, pushes that lead to a deadlock are refused.
+    pub fn set_safe_mode(&mut self, safe_mode: bool) {
+        self.safe_mode = safe_mode;
+    }
+
+    pub fn apply(&mut self, direction: Direction) -> Result<MoveOutcome, MoveError> {
+        self.apply_joined(direction, false)
+    }
+
+    // Makes several moves in one go, so that undo and redo take them back
+    // and play them again as a whole. Nothing is moved if any of them fails.
+    // Returns the outcome of the last move.
+    pub fn apply_batch(&mut self, directions: &[Direction]) -> Result<MoveOutcome, MoveError> {
+        let mut trial = self.clone();
+
+        for direction in directions {
+            trial.apply(*direction)?;
+        }
+
+        let mut outcome = Err(MoveError::Blocked);
+
+        for (i, direction) in directions.iter().enumerate() {
+            outcome = self.apply_joined(*direction, i > 0);
+        }
+
+        outcome
+    }
+
+    fn apply_joined(
+        &mut self,
+        direction: Direction,
+        joined: bool,
+    ) -> Result<MoveOutcome, MoveError> {
+        if self.safe_mode && self.push_deadlocks(direction) {
+            return Err(MoveError::Deadlock);
+        }
+
+        let outcome = self.step(direction)?;
+
+        self.history.record(outcome.player_move, joined);
+
+        Ok(outcome)
+    }
+
+    fn step(&mut self, direction: Direction) -> Result<MoveOutcome, MoveError> {
+        let player_move = self.player.try_move(direction, &self.level, &self.boxes)?;
+
+        if let Some(box_id) = player_move.box_id {
+            self.boxes
+                .iter_mut()
+                .filter(|b| *b.get_id() == box_id)
+                .for_each(|b| *b.get_position_mut() += player_move.delta);
+        }
+
+        self.moves.push(player_move);
+
+        Ok(MoveOutcome {
+            player_move,
+            solved: self.is_solved(),
+        })
+    }
+
+    pub fn undo(&mut self) -> Option<PlayerMove> {
+        let last_move = self.history.undo()?;
+
+        self.moves.pop();
+
+        self.player.position -= last_move.delta;
+
+        if let Some(id) = last_move.box_id {
+            let b = self.boxes.iter_mut().find(|b| *b.get_id() == id).unwrap();
+
+            *b.get_position_mut() -= last_move.delta;
+        }
+
+        Some(last_move)
+    }
+
+    // Plays the next move of the selected branch in the history again.
+    pub fn redo(&mut self) -> Option<MoveOutcome> {
+        let next_move = self.history.redo()?;
+        let direction = Direction::from_delta(next_move.delta)?;
+
+        // Boxes keep their ids, so the move comes out the same as before
+        self.step(direction).ok()
+    }
+
+    // Undoes the last move together with the moves made in one go with it.
+    // Returns the number of moves undone.
+    pub fn undo_batch(&mut self) -> usize {
+        let mut undone = 0;
+
+        loop {
+            let joined = self.history.is_joined();
+
+            if self.undo().is_none() {
+                break;
+            }
+
+            undone += 1;
+
+            if !joined {
+                break;
+            }
+        }
+
+        undone
+    }
+
+    // Redoes the next move together with the moves made in one go with it.
+    // Returns the number of moves redone.
+    pub fn redo_batch(&mut self) -> usize {
+        let mut redone = 0;
+
+        while self.redo().is_some() {
+            redone += 1;
+
+            if !self.history.is_next_joined() {
+                break;
+            }
+        }
+
+        redone
+    }
+
+    pub fn next_branch(&mut self) {
+        self.history.next_branch();
+    }
+
+    // Goes back to the starting position. The moves stay in the history and
+    // can be redone.
+    pub fn reset(&mut self) {
+        while self.undo().is_some() {}
+    }
+
+    // The current position as a level of its own, so it can be shared or
+    // used as a new starting point.
+    pub fn to_level(&self) -> Level {
+        let mut level = self.level.clone();
+
+        level.player = self.player.position;
+        level.boxes = self.boxes.iter().map(|b| *b.get_position()).collect();
+
+        level
+    }
+
+    pub fn to_xsb(&self) -> String {
+        self.to_level().to_xsb()
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.level.is_solved(&self.boxes)
+    }
+
+    // Whether a box on this square can never reach a target: corners, and
+    // squares along walls that no target can be pushed to.
+    pub fn is_dead_square(&self, point: Point2D) -> bool {
+        self.level.get_tile(point.x, point.y).is_some()
+            && self.board.is_dead(self.board.index(point))
+    }
+
+    // Boxes that can't reach a target anymore, because they are on a dead
+    // square or frozen in place off target.
+    pub fn deadlocked_boxes(&self) -> Vec<Uuid> {
+        let occupied = self.occupied_squares(&self.boxes);
+
+        self.boxes
+            .iter()
+            .filter(|b| {
+                let cell = self.board.index(*b.get_position());
+
+                self.board.is_dead(cell) || self.board.is_freeze_deadlock(cell, &occupied)
+            })
+            .map(|b| *b.get_id())
+            .collect()
+    }
+
+    // Whether moving in `direction` pushes a box into a deadlock.
+    fn push_deadlocks(&self, direction: Direction) -> bool {
+        let delta = direction.delta();
+        let target = self.player.position + delta;
+        let Some(pushed) = self.boxes.iter().position(|b| *b.get_position() == target) else {
+            return false;
+        };
+
+        if !self.boxes[pushed].can_be_moved(delta, &self.level, &self.boxes) {
+            return false;
+        }
+
+        let mut boxes = self.boxes.clone();
+
+        *boxes[pushed].get_position_mut() += delta;
+
+        let cell = self.board.index(*boxes[pushed].get_position());
+
+        self.board.is_dead(cell)
+            || self
+                .board
+                .is_freeze_deadlock(cell, &self.occupied_squares(&boxes))
+    }
+
+    fn occupied_squares(&self, boxes: &[MovableBox]) -> Vec<bool> {
+        let mut occupied = vec![false; self.board.width * self.board.height];
+
+        for b in boxes {
+            occupied[self.board.index(*b.get_position())] = true;
+        }
+
+        occupied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(board: &str) -> Game {
+        let mut levels = LevelManager::new();
+
+        levels.load_from_str(board, None).unwrap();
+
+        Game::new(levels.get_level(0, 0).unwrap().clone())
+    }
+
+    // The squares of the boxes reported as deadlocked
+    fn deadlocked(game: &Game) -> Vec<Point2D> {
+        let ids = game.deadlocked_boxes();
+
+        game.boxes()
+            .iter()
+            .filter(|b| ids.contains(b.get_id()))
+            .map(|b| *b.get_position())
+            .collect()
+    }
+
+    #[test]
+    fn batches_are_undone_and_redone_as_a_whole() {
+        let mut game = game("#######\n#@ $ .#\n#######");
+
+        game.apply(Direction::Right).unwrap();
+        game.apply_batch(&[Direction::Right, Direction::Right])
+            .unwrap();
+
+        assert_eq!(game.to_lurd(), "rRR");
+        assert!(game.is_solved());
+        assert_eq!(game.undo_batch(), 2);
+        assert_eq!(game.to_lurd(), "r");
+        assert_eq!(game.redo_batch(), 2);
+        assert_eq!(game.to_lurd(), "rRR");
+
+        // A blocked batch doesn't move anything
+        game.reset();
+
+        assert!(game
+            .apply_batch(&[Direction::Right, Direction::Up])
+            .is_err());
+        assert_eq!(game.to_lurd(), "");
+        assert_eq!(game.redo_batch(), 1);
+    }
+
+    #[test]
+    fn boxes_in_corners_are_deadlocked() {
+        let game = game("#####\n#$ .#\n#@  #\n#####");
+
+        assert_eq!(deadlocked(&game), [Point2D { x: 1, y: 1 }]);
+    }
+
+    #[test]
+    fn boxes_frozen_against_a_wall_are_deadlocked() {
+        // Neither box is on a dead square, but they hold each other in place
+        let game = game("#######\n# $$. #\n#.  @ #\n#######");
+
+        assert_eq!(
+            deadlocked(&game),
+            [Point2D { x: 2, y: 1 }, Point2D { x: 3, y: 1 }]
+        );
+    }
+
+    #[test]
+    fn boxes_on_targets_are_not_deadlocked() {
+        let game = game("#####\n#*  #\n#@$.#\n#####");
+
+        assert!(deadlocked(&game).is_empty());
+    }
+
+    #[test]
+    fn safe_mode_refuses_deadlocking_pushes() {
+        let mut game = game("#######\n#     #\n#  $@ #\n#.    #\n#######");
+
+        game.set_safe_mode(true);
+        game.apply(Direction::Down).unwrap();
+        game.apply(Direction::Left).unwrap();
+
+        // Against the top wall, where there is no target
+        assert_eq!(game.apply(Direction::Up), Err(MoveError::Deadlock));
+        assert_eq!(game.to_lurd(), "dl");
+
+        game.set_safe_mode(false);
+        game.apply(Direction::Up).unwrap();
+
+        assert_eq!(deadlocked(&game), [Point2D { x: 3, y: 1 }]);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::prelude::*;
+
+// Pushes needed to get a box from a square to a target, if it can get there
+pub(super) const UNREACHABLE: u32 = u32::MAX;
+
+// The static part of a level, prepared for the solver. Squares are indexed
+// row by row like the tiles of a `Level`.
+#[derive(Clone, Debug)]
+pub struct Board {
+    pub width: usize,
+    pub height: usize,
+    floor: Vec<bool>,
+    targets: Vec<bool>,
+    target_list: Vec<usize>,
+    // Pushes from every square to every target, ignoring other boxes
+    distances: Vec<Vec<u32>>,
+    dead: Vec<bool>,
+}
+
+impl Board {
+    pub fn new(level: &Level) -> Self {
+        let width = level.width as usize;
+        let height = level.height as usize;
+        let mut floor = vec![false; width * height];
+        let mut targets = vec![false; width * height];
+
+        for y in 0..level.height {
+            for x in 0..level.width {
+                floor[y as usize * width + x as usize] = level.is_accessible(Point2D { x, y });
+            }
+        }
+
+        for target in &level.targets {
+            targets[target.y as usize * width + target.x as usize] = true;
+        }
+
+        let mut board = Self {
+            width,
+            height,
+            floor,
+            target_list: (0..width * height).filter(|i| targets[*i]).collect(),
+            targets,
+            distances: Vec::new(),
+            dead: Vec::new(),
+        };
+
+        board.distances = board
+            .target_list
+            .iter()
+            .map(|t| board.pull_distances(*t))
+            .collect();
+        board.dead = (0..width * height)
+            .map(|cell| board.distances.iter().all(|d| d[cell] == UNREACHABLE))
+            .collect();
+
+        board
+    }
+
+    pub fn index(&self, point: Point2D) -> usize {
+        point.y as usize * self.width + point.x as usize
+    }
+
+    pub fn point(&self, cell: usize) -> Point2D {
+        Point2D {
+            x: (cell % self.width) as i32,
+            y: (cell / self.width) as i32,
+        }
+    }
+
+    // The square next to `cell` in the given direction, if it is on the board.
+    pub fn step(&self, cell: usize, direction: Direction) -> Option<usize> {
+        let point = self.point(cell) + direction.delta();
+
+        if point.x < 0
+            || point.y < 0
+            || point.x as usize >= self.width
+            || point.y as usize >= self.height
+        {
+            return None;
+        }
+
+        Some(self.index(point))
+    }
+
+    pub fn is_floor(&self, cell: usize) -> bool {
+        self.floor[cell]
+    }
+
+    pub fn is_target(&self, cell: usize) -> bool {
+        self.targets[cell]
+    }
+
+    pub fn targets(&self) -> &[usize] {
+        &self.target_list
+    }
+
+    // Whether a box on this square can never reach any target.
+    pub fn is_dead(&self, cell: usize) -> bool {
+        self.dead[cell]
+    }
+
+    pub fn distance(&self, target: usize, cell: usize) -> u32 {
+        self.distances[target][cell]
+    }
+
+    // Finds how many pushes every square is away from a target by pulling a
+    // box backwards from it. A box can be pulled when the player has room to
+    // step back.
+    fn pull_distances(&self, target: usize) -> Vec<u32> {
+        let mut distances = vec![UNREACHABLE; self.width * self.height];
+        let mut queue = std::collections::VecDeque::from([target]);
+
+        distances[target] = 0;
+
+        while let Some(cell) = queue.pop_front() {
+            for direction in Direction::ALL {
+                let Some(next) = self.step(cell, direction) else {
+                    continue;
+                };
+                let Some(player) = self.step(next, direction) else {
+                    continue;
+                };
+
+                if self.floor[next] && self.floor[player] && distances[next] == UNREACHABLE {
+                    distances[next] = distances[cell] + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        distances
+    }
+
+    // All squares the player can walk to without pushing, and the topmost
+    // leftmost of them, which stands for the whole area.
+    pub fn reachable(&self, player: usize, boxes: &[bool]) -> (Vec<bool>, usize) {
+        let mut reachable = vec![false; self.width * self.height];
+        let mut stack = vec![player];
+        let mut first = player;
+
+        reachable[player] = true;
+
+        while let Some(cell) = stack.pop() {
+            first = first.min(cell);
+
+            for direction in Direction::ALL {
+                if let Some(next) = self.step(cell, direction) {
+                    if self.floor[next] && !boxes[next] && !reachable[next] {
+                        reachable[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+
+        (reachable, first)
+    }
+
+    // The shortest walk from one square to another that doesn't move any
+    // boxes.
+    pub fn walk(&self, from: usize, to: usize, boxes: &[bool]) -> Option<Vec<Direction>> {
+        let mut came_from: Vec<Option<(usize, Direction)>> = vec![None; self.width * self.height];
+        let mut queue = std::collections::VecDeque::from([from]);
+        let mut visited = vec![false; self.width * self.height];
+
+        visited[from] = true;
+
+        while let Some(cell) = queue.pop_front() {
+            if cell == to {
+                let mut path = Vec::new();
+                let mut current = to;
+
+                while let Some((previous, direction)) = came_from[current] {
+                    path.push(direction);
+                    current = previous;
+                }
+
+                path.reverse();
+
+                return Some(path);
+            }
+
+            for direction in Direction::ALL {
+                if let Some(next) = self.step(cell, direction) {
+                    if self.floor[next] && !boxes[next] && !visited[next] {
+                        visited[next] = true;
+                        came_from[next] = Some((cell, direction));
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
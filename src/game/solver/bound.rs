@@ -0,0 +1,102 @@
+use super::board::{Board, UNREACHABLE};
+
+// Stands in for a box that can't reach a target. Large enough to never be
+// part of a real solution, small enough to add up without overflowing.
+const INFINITE: i64 = 1 << 40;
+
+impl Board {
+    // A lower bound for the pushes needed to solve the position: every box is
+    // matched to its own target so that the sum of the distances is as small
+    // as possible. Returns None if there is no matching at all.
+    pub fn lower_bound(&self, boxes: &[usize]) -> Option<u32> {
+        let targets = self.targets();
+        let costs: Vec<Vec<i64>> = boxes
+            .iter()
+            .map(|b| {
+                (0..targets.len())
+                    .map(|t| match self.distance(t, *b) {
+                        UNREACHABLE => INFINITE,
+                        d => d as i64,
+                    })
+                    .collect()
+            })
+            .collect();
+        let total = min_cost_matching(&costs);
+
+        (total < INFINITE).then_some(total as u32)
+    }
+}
+
+// The Hungarian algorithm for an n by m cost matrix with n <= m. Returns the
+// smallest total cost of assigning every row to a different column.
+fn min_cost_matching(costs: &[Vec<i64>]) -> i64 {
+    let n = costs.len();
+    let m = costs.first().map_or(0, Vec::len);
+    // Potentials and matches are 1-based, column 0 is a placeholder
+    let mut u = vec![0; n + 1];
+    let mut v = vec![0; m + 1];
+    let mut matched = vec![0; m + 1];
+    let mut way = vec![0; m + 1];
+
+    for row in 1..=n {
+        let mut min_values = vec![i64::MAX; m + 1];
+        let mut used = vec![false; m + 1];
+        let mut column = 0;
+
+        matched[0] = row;
+
+        loop {
+            let current = matched[column];
+            let mut delta = i64::MAX;
+            let mut next = 0;
+
+            used[column] = true;
+
+            for j in 1..=m {
+                if used[j] {
+                    continue;
+                }
+
+                let reduced = costs[current - 1][j - 1] - u[current] - v[j];
+
+                if reduced < min_values[j] {
+                    min_values[j] = reduced;
+                    way[j] = column;
+                }
+
+                if min_values[j] < delta {
+                    delta = min_values[j];
+                    next = j;
+                }
+            }
+
+            for j in 0..=m {
+                if used[j] {
+                    u[matched[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_values[j] -= delta;
+                }
+            }
+
+            column = next;
+
+            if matched[column] == 0 {
+                break;
+            }
+        }
+
+        // Flip the matches along the augmenting path
+        while column != 0 {
+            let previous = way[column];
+
+            matched[column] = matched[previous];
+            column = previous;
+        }
+    }
+
+    (1..=m)
+        .filter(|j| matched[*j] != 0)
+        .map(|j| costs[matched[j] - 1][j - 1])
+        .sum()
+}
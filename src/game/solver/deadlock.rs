@@ -0,0 +1,69 @@
+use super::board::Board;
+use crate::prelude::*;
+
+impl Board {
+    // Whether the box on `cell` can't be moved anymore, neither horizontally
+    // nor vertically, while it or one of the boxes holding it in place is off
+    // target. Such a position can never be solved.
+    pub fn is_freeze_deadlock(&self, cell: usize, boxes: &[bool]) -> bool {
+        let mut fixed = vec![false; boxes.len()];
+        let mut frozen = Vec::new();
+
+        self.is_frozen(cell, boxes, &mut fixed, &mut frozen)
+            && frozen.iter().any(|c| !self.is_target(*c))
+    }
+
+    // Boxes that are being checked count as walls, which keeps boxes that
+    // block each other from going around in circles. Every box found to be
+    // frozen is added to `frozen`.
+    fn is_frozen(
+        &self,
+        cell: usize,
+        boxes: &[bool],
+        fixed: &mut [bool],
+        frozen: &mut Vec<usize>,
+    ) -> bool {
+        fixed[cell] = true;
+
+        let is_frozen = self.is_blocked(cell, Direction::Left, boxes, fixed, frozen)
+            && self.is_blocked(cell, Direction::Up, boxes, fixed, frozen);
+
+        fixed[cell] = false;
+
+        if is_frozen {
+            frozen.push(cell);
+        }
+
+        is_frozen
+    }
+
+    // Whether the box can't move along the axis of `direction`.
+    fn is_blocked(
+        &self,
+        cell: usize,
+        direction: Direction,
+        boxes: &[bool],
+        fixed: &mut [bool],
+        frozen: &mut Vec<usize>,
+    ) -> bool {
+        let sides = [
+            self.step(cell, direction),
+            self.step(cell, direction.opposite()),
+        ];
+        let is_wall = |side: Option<usize>| side.is_none_or(|c| !self.is_floor(c) || fixed[c]);
+
+        if sides.iter().any(|side| is_wall(*side)) {
+            return true;
+        }
+
+        // Pushing the box either way would put it on a dead square
+        if sides.iter().flatten().all(|c| self.is_dead(*c)) {
+            return true;
+        }
+
+        sides
+            .iter()
+            .flatten()
+            .any(|c| boxes[*c] && self.is_frozen(*c, boxes, fixed, frozen))
+    }
+}
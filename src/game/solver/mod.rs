@@ -0,0 +1,377 @@
+mod board;
+mod bound;
+mod deadlock;
+
+pub use board::Board;
+
+use crate::prelude::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::time::{Duration, Instant};
+
+// How many positions are explored between two looks at the clock
+const CLOCK_INTERVAL: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SolverLimits {
+    pub time: Option<Duration>,
+    // Rough upper bound for the memory used by the search, in bytes
+    pub memory: Option<usize>,
+}
+
+impl Default for SolverLimits {
+    fn default() -> Self {
+        Self {
+            time: Some(Duration::from_secs(10)),
+            memory: Some(512 * 1024 * 1024),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SolverError {
+    // The search ran out of positions, so there is no solution at all
+    Unsolvable,
+    TimeLimit,
+    MemoryLimit,
+}
+
+impl std::fmt::Display for SolverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SolverError::Unsolvable => write!(f, "there is no solution"),
+            SolverError::TimeLimit => write!(f, "no solution found within the time limit"),
+            SolverError::MemoryLimit => write!(f, "no solution found within the memory limit"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution {
+    pub lurd: String,
+    pub pushes: usize,
+    // Number of positions looked at to find the solution
+    pub explored: usize,
+}
+
+// A position as seen by the solver: the squares of the boxes in ascending
+// order, and the topmost leftmost square the player can walk to, which
+// stands for every position of the player within the same area.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Position {
+    boxes: Box<[u32]>,
+    player: u32,
+}
+
+struct Node {
+    position: Position,
+    pushes: u32,
+    parent: Option<usize>,
+    // The square of the box pushed to get here, and the direction
+    push: Option<(u32, Direction)>,
+    closed: bool,
+}
+
+// Finds a solution with the fewest pushes, starting from the position of the
+// player and the boxes in `level`. This is an A* search over pushes, guided
+// by a lower bound that matches boxes to targets. Positions with boxes on
+// dead squares or frozen off target are never looked at.
+pub fn solve(level: &Level, limits: &SolverLimits) -> Result<Solution, SolverError> {
+    let started = Instant::now();
+    let board = Board::new(level);
+    let mut occupied = vec![false; board.width * board.height];
+
+    if level.boxes.len() != level.targets.len() {
+        return Err(SolverError::Unsolvable);
+    }
+
+    let mut start_boxes: Vec<u32> = level.boxes.iter().map(|b| board.index(*b) as u32).collect();
+
+    start_boxes.sort_unstable();
+    set_boxes(&mut occupied, &start_boxes, true);
+
+    let (_, start_player) = board.reachable(board.index(level.player), &occupied);
+    let start = Position {
+        boxes: start_boxes.clone().into_boxed_slice(),
+        player: start_player as u32,
+    };
+    let start_bound = lower_bound(&board, &start.boxes).ok_or(SolverError::Unsolvable)?;
+
+    if start_boxes
+        .iter()
+        .any(|b| board.is_freeze_deadlock(*b as usize, &occupied))
+    {
+        return Err(SolverError::Unsolvable);
+    }
+
+    set_boxes(&mut occupied, &start_boxes, false);
+
+    // Every node is stored twice, in the list and as a key of the table
+    let node_size = 2 * (start_boxes.len() * 4 + std::mem::size_of::<Node>()) + 32;
+    let mut nodes = vec![Node {
+        position: start.clone(),
+        pushes: 0,
+        parent: None,
+        push: None,
+        closed: false,
+    }];
+    let mut table: HashMap<Position, usize> = HashMap::from([(start, 0)]);
+    // Ordered by estimated total pushes, then by the pushes still to go
+    let mut open = BinaryHeap::from([Reverse((start_bound, start_bound, 0))]);
+    let mut explored = 0;
+
+    while let Some(Reverse((_, bound, idx))) = open.pop() {
+        if nodes[idx].closed {
+            continue;
+        }
+
+        nodes[idx].closed = true;
+        explored += 1;
+
+        if bound == 0 {
+            return Ok(Solution {
+                lurd: to_lurd(&board, level, &nodes, idx),
+                pushes: nodes[idx].pushes as usize,
+                explored,
+            });
+        }
+
+        if explored % CLOCK_INTERVAL == 0 && limits.time.is_some_and(|t| started.elapsed() > t) {
+            return Err(SolverError::TimeLimit);
+        }
+
+        if limits.memory.is_some_and(|m| nodes.len() * node_size > m) {
+            return Err(SolverError::MemoryLimit);
+        }
+
+        let position = nodes[idx].position.clone();
+        let pushes = nodes[idx].pushes + 1;
+
+        set_boxes(&mut occupied, &position.boxes, true);
+
+        let (reachable, _) = board.reachable(position.player as usize, &occupied);
+
+        for (i, b) in position.boxes.iter().enumerate() {
+            let b = *b as usize;
+
+            for direction in Direction::ALL {
+                let Some(destination) = board.step(b, direction) else {
+                    continue;
+                };
+                let Some(behind) = board.step(b, direction.opposite()) else {
+                    continue;
+                };
+
+                if !reachable[behind]
+                    || !board.is_floor(destination)
+                    || occupied[destination]
+                    || board.is_dead(destination)
+                {
+                    continue;
+                }
+
+                occupied[b] = false;
+                occupied[destination] = true;
+
+                let frozen = board.is_freeze_deadlock(destination, &occupied);
+                let (_, player) = board.reachable(b, &occupied);
+
+                occupied[destination] = false;
+                occupied[b] = true;
+
+                if frozen {
+                    continue;
+                }
+
+                let mut boxes = position.boxes.to_vec();
+
+                boxes[i] = destination as u32;
+                boxes.sort_unstable();
+
+                let child = Position {
+                    boxes: boxes.into_boxed_slice(),
+                    player: player as u32,
+                };
+                let child_idx = match table.get(&child) {
+                    Some(existing) if nodes[*existing].closed => continue,
+                    Some(existing) if nodes[*existing].pushes <= pushes => continue,
+                    Some(existing) => *existing,
+                    None => {
+                        nodes.push(Node {
+                            position: child.clone(),
+                            pushes,
+                            parent: None,
+                            push: None,
+                            closed: false,
+                        });
+                        table.insert(child.clone(), nodes.len() - 1);
+
+                        nodes.len() - 1
+                    }
+                };
+                let Some(child_bound) = lower_bound(&board, &child.boxes) else {
+                    continue;
+                };
+
+                nodes[child_idx].pushes = pushes;
+                nodes[child_idx].parent = Some(idx);
+                nodes[child_idx].push = Some((b as u32, direction));
+                open.push(Reverse((pushes + child_bound, child_bound, child_idx)));
+            }
+        }
+
+        set_boxes(&mut occupied, &position.boxes, false);
+    }
+
+    Err(SolverError::Unsolvable)
+}
+
+fn lower_bound(board: &Board, boxes: &[u32]) -> Option<u32> {
+    let boxes: Vec<usize> = boxes.iter().map(|b| *b as usize).collect();
+
+    board.lower_bound(&boxes)
+}
+
+fn set_boxes(occupied: &mut [bool], boxes: &[u32], value: bool) {
+    for b in boxes {
+        occupied[*b as usize] = value;
+    }
+}
+
+// Writes out the pushes leading to a node, with the walks in between.
+fn to_lurd(board: &Board, level: &Level, nodes: &[Node], idx: usize) -> String {
+    let mut pushes = Vec::new();
+    let mut current = Some(idx);
+
+    while let Some(node) = current.map(|i| &nodes[i]) {
+        pushes.extend(node.push);
+        current = node.parent;
+    }
+
+    pushes.reverse();
+
+    let mut occupied = vec![false; board.width * board.height];
+    let mut player = board.index(level.player);
+    let mut lurd = String::new();
+
+    for b in &level.boxes {
+        occupied[board.index(*b)] = true;
+    }
+
+    for (b, direction) in pushes {
+        let b = b as usize;
+        let behind = board.step(b, direction.opposite()).unwrap();
+        let walk = board.walk(player, behind, &occupied).unwrap();
+
+        for step in walk {
+            lurd.push(
+                LurdStep {
+                    direction: step,
+                    push: false,
+                }
+                .to_char(),
+            );
+        }
+
+        lurd.push(
+            LurdStep {
+                direction,
+                push: true,
+            }
+            .to_char(),
+        );
+
+        occupied[b] = false;
+        occupied[board.step(b, direction).unwrap()] = true;
+        player = b;
+    }
+
+    lurd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(board: &str) -> Level {
+        let mut levels = LevelManager::new();
+
+        levels.load_from_str(board, None).unwrap();
+        levels.get_level(0, 0).unwrap().clone()
+    }
+
+    fn unlimited() -> SolverLimits {
+        SolverLimits {
+            time: None,
+            memory: None,
+        }
+    }
+
+    // Solves the level and checks the solution by playing it
+    fn solved_pushes(board: &str) -> usize {
+        let level = level(board);
+        let solution = solve(&level, &unlimited()).unwrap();
+        let game = verify_solution(&level, &solution.lurd).unwrap();
+
+        assert_eq!(game.statistics().pushes, solution.pushes);
+
+        solution.pushes
+    }
+
+    #[test]
+    fn finds_the_fewest_pushes() {
+        assert_eq!(solved_pushes("#####\n#@$.#\n#####"), 1);
+        assert_eq!(solved_pushes("#######\n#@$  .#\n#######"), 3);
+        assert_eq!(solved_pushes("#######\n#.$ $.#\n#  @  #\n#######"), 2);
+        // Around a corner
+        assert_eq!(solved_pushes("######\n#.   #\n#  $ #\n# @  #\n######"), 3);
+    }
+
+    #[test]
+    fn solves_boards_with_many_squares() {
+        // More squares than fit in 16 bits, with the action at the far end
+        let wall = "#".repeat(70_000);
+        let board = format!("{}\n#{}@$.#\n{}", wall, " ".repeat(69_995), wall);
+
+        assert_eq!(solved_pushes(&board), 1);
+    }
+
+    #[test]
+    fn reports_unsolvable_levels() {
+        let level = level("######\n#@  .#\n#$   #\n######");
+
+        assert_eq!(solve(&level, &unlimited()), Err(SolverError::Unsolvable));
+    }
+
+    #[test]
+    fn stops_at_the_time_limit() {
+        // Takes a couple of thousand positions to solve
+        let level = level(
+            "############\n\
+             #@         #\n\
+             # $ $ $ $  #\n\
+             #  $ $ $   #\n\
+             #          #\n\
+             #  ....    #\n\
+             #    ...   #\n\
+             ############",
+        );
+        let limits = SolverLimits {
+            time: Some(Duration::ZERO),
+            memory: None,
+        };
+
+        assert_eq!(solve(&level, &limits), Err(SolverError::TimeLimit));
+    }
+
+    #[test]
+    fn stops_at_the_memory_limit() {
+        let level = level("#######\n#@$  .#\n#######");
+        let limits = SolverLimits {
+            time: None,
+            memory: Some(0),
+        };
+
+        assert_eq!(solve(&level, &limits), Err(SolverError::MemoryLimit));
+    }
+}
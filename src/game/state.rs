@@ -1,27 +1,119 @@
-use crate::prelude::*;
+use crate::gfx_prelude::*;
+use std::io::Write;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+
+// Number of packs listed at once by the pack browser
+const BROWSER_ROWS: usize = 14;
+
+// Time between two moves of a replay
+const REPLAY_INTERVAL: Duration = Duration::from_millis(150);
+
+// How hard the solver tries to find a hint
+const HINT_LIMITS: SolverLimits = SolverLimits {
+    time: Some(Duration::from_secs(3)),
+    memory: Some(256 * 1024 * 1024),
+};
+
+// Layout of the level select grid, sizes in pixels
+const GRID_COLUMNS: usize = 5;
+const GRID_ROWS: usize = 3;
+const GRID_TOP: f32 = 96.0;
+const CELL_WIDTH: f32 = 240.0;
+const CELL_HEIGHT: f32 = 200.0;
+const THUMBNAIL_WIDTH: f32 = 208.0;
+const THUMBNAIL_HEIGHT: f32 = 150.0;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum GameState {
+    Browsing,
+    LevelSelect,
     Playing,
+    Replaying,
     Solved,
 }
 
+// A LURD string being played back move by move
+struct Replay {
+    steps: Vec<LurdStep>,
+    next: usize,
+    paused: bool,
+    last_step: Instant,
+    error: Option<LurdError>,
+}
+
+// A box being dragged with the mouse to where it should be pushed
+struct Drag {
+    from: Point2D,
+    to: Point2D,
+    // How to get the box there, if it can be done
+    path: Option<Vec<Direction>>,
+}
+
 pub struct State {
     levels: LevelManager,
     sprites: SpriteManager,
+    current_collection: usize,
     current_level: usize,
-    player: Player,
-    boxes: Vec<MovableBox>,
-    moves: Vec<PlayerMove>,
+    selected_collection: usize,
+    selected_level: usize,
+    // First row of the level select grid that is on screen
+    first_row: usize,
+    progress: Progress,
+    game: Game,
     game_state: GameState,
+    // When the current attempt at the level started
+    started: Instant,
+    // How long it took to solve the level, and whether that set a new best
+    solve_time: Duration,
+    new_record: bool,
+    replay: Option<Replay>,
+    // Whether moves of the current attempt came from a replay, which keeps
+    // the attempt out of the records
+    replayed: bool,
+    // The last hint and the moves leading to the position it is for
+    hint: Option<(Hint, Vec<PlayerMove>)>,
+    // Hints are searched for in the background, like the hint above
+    hint_request: Option<(Receiver<Hint>, Vec<PlayerMove>)>,
+    hints_used: usize,
+    drag: Option<Drag>,
+    // The box whose push targets are shown, and those targets together with
+    // the moves leading to the position they are for
+    selected_box: Option<Uuid>,
+    push_targets: Option<(Vec<PlayerMove>, Vec<Point2D>)>,
+    // Whether the tiles the player can walk to are shaded
+    show_reachable: bool,
+    animator: Animator,
+    // Arrow key presses that haven't been played yet
+    input: InputQueue,
 }
 
 impl State {
-    pub fn new(ctx: &mut Context) -> Result<Self, String> {
-        let mut lm = LevelManager::new();
-        let current_level = 0;
+    // Starts playing the given collection and level. Without one, the level
+    // played last is continued, or the pack browser is shown first if there
+    // is more than one pack to choose from.
+    pub fn new(
+        ctx: &mut Context,
+        lm: LevelManager,
+        start: Option<(usize, usize)>,
+    ) -> Result<Self, String> {
+        let progress = Progress::load(ctx)?;
+        let start = start.or_else(|| {
+            progress
+                .last_level()
+                .and_then(|fingerprint| lm.find_fingerprint(fingerprint))
+        });
+        let (current_collection, current_level) = start.unwrap_or((0, 0));
+
+        if lm.get_level(current_collection, current_level).is_none() {
+            return Err(String::from("There is no level to play"));
+        }
 
-        lm.load_from_file(ctx, "/levels.txt")?;
+        let game_state = if start.is_none() && lm.num_collections() > 1 {
+            GameState::Browsing
+        } else {
+            GameState::Playing
+        };
 
         let mut sm = SpriteManager::new();
 
@@ -40,117 +132,1176 @@ impl State {
             Err(e) => return Err(e.to_string()),
         }
 
-        let player_pos = lm.get_level(current_level).unwrap().player;
-        let mut boxes = Vec::new();
+        let mut game = Game::new(
+            lm.get_level(current_collection, current_level)
+                .unwrap()
+                .clone(),
+        );
 
-        for box_pos in &lm.get_level(current_level).unwrap().boxes {
-            boxes.push(MovableBox::new(*box_pos));
-        }
+        game.set_safe_mode(progress.safe_mode());
+
+        let mut animator = Animator::new();
+
+        animator.reset(&game);
 
         Ok(Self {
             levels: lm,
             sprites: sm,
+            current_collection,
             current_level,
-            player: Player::new(player_pos),
-            boxes,
-            moves: Vec::new(),
-            game_state: GameState::Playing,
+            selected_collection: current_collection,
+            selected_level: current_level,
+            first_row: 0,
+            progress,
+            game,
+            game_state,
+            started: Instant::now(),
+            solve_time: Duration::ZERO,
+            new_record: false,
+            replay: None,
+            replayed: false,
+            hint: None,
+            hint_request: None,
+            hints_used: 0,
+            drag: None,
+            selected_box: None,
+            push_targets: None,
+            show_reachable: false,
+            animator,
+            input: InputQueue::new(KeyRepeat::default()),
         })
     }
 
-    fn get_current_level(&self) -> Option<&Level> {
-        self.levels.get_level(self.current_level)
+    // Tiles per second the player and boxes move at, 0 shows every move
+    // right away.
+    pub fn set_animation_speed(&mut self, speed: f32) {
+        self.animator.set_speed(speed);
+    }
+
+    // How held arrow keys repeat their move.
+    pub fn set_key_repeat(&mut self, repeat: KeyRepeat) {
+        self.input.set_repeat(repeat);
+    }
+
+    // Plays a solution in LURD notation back from the start of the current
+    // level. Moves that turn out to be illegal stop the replay.
+    pub fn replay(&mut self, solution: &str) -> Result<(), LurdError> {
+        let steps = parse_lurd(solution)?;
+
+        self.game = Game::new(self.game.level().clone());
+        self.game.set_safe_mode(self.progress.safe_mode());
+        self.animator.reset(&self.game);
+        self.game_state = GameState::Replaying;
+        self.replayed = true;
+        self.replay = Some(Replay {
+            steps,
+            next: 0,
+            paused: false,
+            last_step: Instant::now(),
+            error: None,
+        });
+
+        Ok(())
+    }
+
+    fn update_replaying(&mut self, ctx: &Context) {
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Escape) {
+            // Play on from wherever the replay got to
+            self.replay = None;
+            self.game_state = GameState::Playing;
+            return;
+        }
+
+        let Some(replay) = &mut self.replay else {
+            return;
+        };
+
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Space) {
+            replay.paused = !replay.paused;
+        }
+
+        if replay.paused || replay.error.is_some() || replay.last_step.elapsed() < REPLAY_INTERVAL {
+            return;
+        }
+
+        if let Some(step) = replay.steps.get(replay.next) {
+            match self.game.apply_lurd(replay.next, *step) {
+                Ok(_) => replay.next += 1,
+                Err(e) => replay.error = Some(e),
+            }
+
+            replay.last_step = Instant::now();
+        }
+    }
+
+    fn play_level(&mut self, collection: usize, level: usize) {
+        let new_level = self.levels.get_level(collection, level).unwrap().clone();
+        let fingerprint = self.levels.get_fingerprint(collection, level).unwrap();
+
+        self.progress.set_last_level(fingerprint.to_owned());
+        self.current_collection = collection;
+        self.current_level = level;
+        self.game = Game::new(new_level);
+        self.game.set_safe_mode(self.progress.safe_mode());
+        self.animator.reset(&self.game);
+        self.replay = None;
+        self.replayed = false;
+        self.hint = None;
+        self.hint_request = None;
+        self.hints_used = 0;
+        self.drag = None;
+        self.input.clear();
+        self.selected_box = None;
+        self.push_targets = None;
+        self.game_state = GameState::Playing;
+        self.started = Instant::now();
+    }
+
+    fn is_solved(&self, collection: usize, level: usize) -> bool {
+        self.levels
+            .get_fingerprint(collection, level)
+            .is_some_and(|fingerprint| self.progress.is_solved(fingerprint))
+    }
+
+    // The fingerprint of the level being played
+    fn current_fingerprint(&self) -> &str {
+        self.levels
+            .get_fingerprint(self.current_collection, self.current_level)
+            .unwrap_or_default()
+    }
+
+    // Percentage of the levels in a collection that have been solved
+    fn completion(&self, collection: usize) -> usize {
+        let total = self.levels.get_collection(collection).unwrap().num_levels();
+        let solved = (0..total)
+            .filter(|level| self.is_solved(collection, *level))
+            .count();
+
+        solved * 100 / total
+    }
+
+    fn update_browsing(&mut self, ctx: &Context) {
+        let num_collections = self.levels.num_collections();
+
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Up) {
+            self.selected_collection =
+                (self.selected_collection + num_collections - 1) % num_collections;
+        } else if ctx.keyboard.is_key_just_pressed(KeyCode::Down) {
+            self.selected_collection = (self.selected_collection + 1) % num_collections;
+        } else if ctx.keyboard.is_key_just_pressed(KeyCode::Return)
+            || ctx.keyboard.is_key_just_pressed(KeyCode::NumpadEnter)
+        {
+            // Start out on the first level that hasn't been solved yet
+            let collection = self.selected_collection;
+            let num_levels = self.levels.get_collection(collection).unwrap().num_levels();
+            let level = (0..num_levels)
+                .find(|l| !self.is_solved(collection, *l))
+                .unwrap_or(0);
+
+            self.select_level(collection, level);
+        }
+    }
+
+    fn select_level(&mut self, collection: usize, level: usize) {
+        self.current_collection = collection;
+        self.selected_level = level;
+        self.first_row = 0;
+        self.scroll_to_selection();
+        self.game_state = GameState::LevelSelect;
+    }
+
+    fn num_rows(&self) -> usize {
+        let num_levels = self
+            .levels
+            .get_collection(self.current_collection)
+            .unwrap()
+            .num_levels();
+
+        num_levels.div_ceil(GRID_COLUMNS)
+    }
+
+    fn scroll_to_selection(&mut self) {
+        let row = self.selected_level / GRID_COLUMNS;
+
+        if row < self.first_row {
+            self.first_row = row;
+        } else if row >= self.first_row + GRID_ROWS {
+            self.first_row = row + 1 - GRID_ROWS;
+        }
+    }
+
+    // Area of the grid cell showing a level, if it is on screen
+    fn grid_cell(&self, level: usize) -> Option<Rect> {
+        let row = (level / GRID_COLUMNS).checked_sub(self.first_row)?;
+        let column = level % GRID_COLUMNS;
+        let left = (WINDOW_WIDTH * TILE_WIDTH) as f32 - GRID_COLUMNS as f32 * CELL_WIDTH;
+
+        if row >= GRID_ROWS {
+            return None;
+        }
+
+        Some(Rect::new(
+            left / 2.0 + column as f32 * CELL_WIDTH,
+            GRID_TOP + row as f32 * CELL_HEIGHT,
+            CELL_WIDTH,
+            CELL_HEIGHT,
+        ))
+    }
+
+    // Level shown in the grid at the given window position
+    fn level_at(&self, x: f32, y: f32) -> Option<usize> {
+        let num_levels = self
+            .levels
+            .get_collection(self.current_collection)
+            .unwrap()
+            .num_levels();
+        let first = self.first_row * GRID_COLUMNS;
+
+        (first..num_levels.min(first + GRID_ROWS * GRID_COLUMNS)).find(|level| {
+            self.grid_cell(*level)
+                .is_some_and(|cell| cell.contains([x, y]))
+        })
+    }
+
+    fn update_level_select(&mut self, ctx: &Context) {
+        let num_levels = self
+            .levels
+            .get_collection(self.current_collection)
+            .unwrap()
+            .num_levels();
+        let keyboard = &ctx.keyboard;
+
+        if keyboard.is_key_just_pressed(KeyCode::Escape) {
+            self.selected_collection = self.current_collection;
+            self.game_state = GameState::Browsing;
+            return;
+        }
+
+        if keyboard.is_key_just_pressed(KeyCode::Return)
+            || keyboard.is_key_just_pressed(KeyCode::NumpadEnter)
+        {
+            self.play_level(self.current_collection, self.selected_level);
+            return;
+        }
+
+        if keyboard.is_key_just_pressed(KeyCode::M) {
+            let metric = match self.progress.metric() {
+                Metric::Moves => Metric::Pushes,
+                Metric::Pushes => Metric::Moves,
+            };
+
+            self.progress.set_metric(metric);
+        }
+
+        let selected = if keyboard.is_key_just_pressed(KeyCode::Left) {
+            self.selected_level.checked_sub(1)
+        } else if keyboard.is_key_just_pressed(KeyCode::Right) {
+            Some(self.selected_level + 1)
+        } else if keyboard.is_key_just_pressed(KeyCode::Up) {
+            self.selected_level.checked_sub(GRID_COLUMNS)
+        } else if keyboard.is_key_just_pressed(KeyCode::Down) {
+            Some(self.selected_level + GRID_COLUMNS)
+        } else {
+            None
+        };
+
+        if let Some(level) = selected.filter(|l| *l < num_levels) {
+            self.selected_level = level;
+            self.scroll_to_selection();
+        }
+
+        // The mouse selects the level below it and plays it when clicked
+        let position = ctx.mouse.position();
+        let moved = ctx.mouse.delta().x != 0.0 || ctx.mouse.delta().y != 0.0;
+
+        if let Some(level) = self.level_at(position.x, position.y) {
+            if moved {
+                self.selected_level = level;
+            }
+
+            if ctx.mouse.button_just_pressed(MouseButton::Left) {
+                self.play_level(self.current_collection, level);
+            }
+        }
     }
 
     fn update_playing(&mut self, ctx: &Context) {
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Escape) {
+            self.drag = None;
+            self.input.clear();
+            self.select_level(self.current_collection, self.current_level);
+            return;
+        }
+
+        // Arrows still waiting would otherwise be played on top of the
+        // position undo, redo or reset lead to
         if ctx.keyboard.is_key_just_pressed(KeyCode::Back) {
-            self.undo_last_move();
+            self.input.clear();
+
+            if ctx.keyboard.is_mod_active(KeyMods::SHIFT) {
+                self.game.redo_batch();
+            } else {
+                self.game.undo_batch();
+            }
+        } else if ctx.keyboard.is_key_just_pressed(KeyCode::Tab) {
+            self.input.clear();
+            self.game.next_branch();
         } else if ctx.keyboard.is_key_just_pressed(KeyCode::R) {
-            self.reset_level();
+            self.input.clear();
+            self.game.reset();
+            // Walking all the way back would take too long
+            self.animator.reset(&self.game);
+            self.started = Instant::now();
+        } else if ctx.keyboard.is_key_just_pressed(KeyCode::S) {
+            if let Err(e) = self.save_position(ctx) {
+                eprintln!("Could not save position: {}", e);
+            }
+        } else if ctx.keyboard.is_key_just_pressed(KeyCode::C) {
+            if let Err(e) = self.save_solution(ctx) {
+                eprintln!("Could not save solution: {}", e);
+            }
+        } else if ctx.keyboard.is_key_just_pressed(KeyCode::H) {
+            self.request_hint();
+        } else if ctx.keyboard.is_key_just_pressed(KeyCode::D) {
+            let safe_mode = !self.game.safe_mode();
+
+            self.game.set_safe_mode(safe_mode);
+            self.progress.set_safe_mode(safe_mode);
+        } else if ctx.keyboard.is_key_just_pressed(KeyCode::Space) {
+            self.select_next_box();
+        } else if ctx.keyboard.is_key_just_pressed(KeyCode::A) {
+            self.show_reachable = !self.show_reachable;
         }
 
-        let delta = if ctx.keyboard.is_key_just_pressed(KeyCode::Left) {
-            Point2D { x: -1, y: 0 }
-        } else if ctx.keyboard.is_key_just_pressed(KeyCode::Right) {
-            Point2D { x: 1, y: 0 }
-        } else if ctx.keyboard.is_key_just_pressed(KeyCode::Up) {
-            Point2D { x: 0, y: -1 }
-        } else if ctx.keyboard.is_key_just_pressed(KeyCode::Down) {
-            Point2D { x: 0, y: 1 }
+        self.input.update(Instant::now());
+
+        // Moves are played in the order their keys were pressed
+        while let Some(direction) = self.input.pop() {
+            // A blocked move simply leaves the board untouched
+            let _ = self.game.apply(direction);
+
+            // Keys pressed after the last push don't carry over
+            if self.game.is_solved() {
+                self.input.clear();
+            }
+        }
+
+        self.update_mouse(ctx);
+        self.update_push_targets();
+    }
+
+    // Selects the box after the selected one, or none after the last.
+    fn select_next_box(&mut self) {
+        let boxes = self.game.boxes();
+        let next = match self.selected_box {
+            Some(id) => boxes
+                .iter()
+                .position(|b| *b.get_id() == id)
+                .and_then(|i| boxes.get(i + 1)),
+            None => boxes.first(),
+        };
+
+        self.selected_box = next.map(|b| *b.get_id());
+    }
+
+    fn selected_box_position(&self) -> Option<Point2D> {
+        let id = self.selected_box?;
+
+        self.game
+            .boxes()
+            .iter()
+            .find(|b| *b.get_id() == id)
+            .map(|b| *b.get_position())
+    }
+
+    // Finds the push targets of the selected box again whenever the
+    // position changed.
+    fn update_push_targets(&mut self) {
+        let Some(from) = self.selected_box_position() else {
+            self.push_targets = None;
+            return;
+        };
+
+        if self
+            .push_targets
+            .as_ref()
+            .is_none_or(|(moves, _)| moves.as_slice() != self.game.moves())
+        {
+            self.push_targets = Some((self.game.moves().to_vec(), self.game.push_targets(from)));
+        }
+    }
+
+    // Clicking a tile walks the player there, if it can be reached, or
+    // pushes the selected box there if it is one of its push targets.
+    // Clicking a box selects it. Boxes can be dragged to where they should go
+    // as well, and are pushed there when dropped.
+    fn update_mouse(&mut self, ctx: &Context) {
+        let position = ctx.mouse.position();
+        let tile = self.tile_at(position.x, position.y);
+
+        if ctx.mouse.button_just_pressed(MouseButton::Left) {
+            match tile {
+                Some(tile) if self.game.boxes().iter().any(|b| *b.get_position() == tile) => {
+                    self.drag = Some(Drag {
+                        from: tile,
+                        to: tile,
+                        path: None,
+                    });
+                }
+                Some(tile) => {
+                    let is_push_target = self
+                        .push_targets
+                        .as_ref()
+                        .is_some_and(|(_, targets)| targets.contains(&tile));
+
+                    match self.selected_box_position() {
+                        Some(from) if is_push_target => {
+                            self.game.push_box_to(from, tile);
+                        }
+                        _ => {
+                            self.game.walk_to(tile);
+                        }
+                    }
+                }
+                None => (),
+            }
+        }
+
+        if let (Some(drag), Some(tile)) = (&mut self.drag, tile) {
+            if drag.to != tile {
+                drag.to = tile;
+                drag.path = self.game.push_path(drag.from, tile);
+            }
+        }
+
+        if ctx.mouse.button_just_released(MouseButton::Left) {
+            if let Some(drag) = self.drag.take() {
+                if drag.to == drag.from {
+                    self.toggle_selection(drag.from);
+                } else {
+                    // The whole way is undone at once
+                    self.game.push_box_to(drag.from, drag.to);
+                }
+            }
+        }
+    }
+
+    fn toggle_selection(&mut self, tile: Point2D) {
+        let clicked = self
+            .game
+            .boxes()
+            .iter()
+            .find(|b| *b.get_position() == tile)
+            .map(|b| *b.get_id());
+
+        self.selected_box = if clicked == self.selected_box {
+            None
         } else {
-            Point2D { x: 0, y: 0 }
+            clicked
         };
+    }
+
+    // Shades the tiles the player can walk to, if enabled, and marks the
+    // tiles the selected box can be pushed to. Drawn between the level and
+    // the boxes.
+    fn draw_overlay(&self, ctx: &Context, canvas: &mut Canvas, offset: Point2D) -> GameResult {
+        let level = self.game.level();
+        let mut mb = MeshBuilder::new();
+        let mut empty = true;
 
-        if delta.x != 0 || delta.y != 0 {
-            let level = self.get_current_level().unwrap().clone();
+        if self.show_reachable {
+            for (i, reachable) in self.game.reachable_tiles().into_iter().enumerate() {
+                if reachable {
+                    let tile = Point2D {
+                        x: i as i32 % level.width,
+                        y: i as i32 / level.width,
+                    };
 
-            if let Ok(player_move) = self.player.try_move(delta, &level, &self.boxes) {
-                if let Some(box_id) = player_move.box_id {
-                    self.boxes
-                        .iter_mut()
-                        .filter(|b| *b.get_id() == box_id)
-                        .for_each(|b| *b.get_position_mut() += delta);
+                    mb.rectangle(
+                        DrawMode::fill(),
+                        tile_rect(tile, offset),
+                        Color::new(0.3, 0.6, 1.0, 0.3),
+                    )?;
+                    empty = false;
                 }
+            }
+        }
 
-                self.moves.push(player_move);
+        if let Some((_, targets)) = &self.push_targets {
+            for tile in targets {
+                mb.rectangle(
+                    DrawMode::fill(),
+                    tile_rect(*tile, offset),
+                    Color::new(0.2, 1.0, 0.2, 0.35),
+                )?;
+                empty = false;
             }
         }
+
+        if let Some(tile) = self.selected_box_position() {
+            mb.rectangle(DrawMode::stroke(3.0), tile_rect(tile, offset), Color::WHITE)?;
+            empty = false;
+        }
+
+        // Meshes without any vertices can't be drawn
+        if !empty {
+            canvas.draw(&Mesh::from_data(ctx, mb.build()), DrawParam::default());
+        }
+
+        Ok(())
     }
 
-    fn update_solved(&mut self, ctx: &Context) {
-        if ctx.keyboard.is_key_just_pressed(KeyCode::Return)
-            || ctx.keyboard.is_key_just_pressed(KeyCode::NumpadEnter)
-        {
-            self.current_level = (self.current_level + 1) % self.levels.num_levels();
-            self.reset_level();
-            self.game_state = GameState::Playing;
+    // Shows where a dragged box would be pushed along, or marks the tile it
+    // is over in red if it can't get there.
+    fn draw_drag(&self, ctx: &Context, canvas: &mut Canvas, offset: Point2D) -> GameResult {
+        let Some(drag) = &self.drag else {
+            return Ok(());
+        };
+
+        if drag.to == drag.from {
+            return Ok(());
         }
+
+        let mut mb = MeshBuilder::new();
+
+        match &drag.path {
+            Some(path) => {
+                let mut player = self.game.player().position;
+                let mut box_position = drag.from;
+
+                for direction in path {
+                    player += direction.delta();
+
+                    if player == box_position {
+                        box_position += direction.delta();
+                        mb.rectangle(
+                            DrawMode::stroke(2.0),
+                            tile_rect(box_position, offset),
+                            Color::CYAN,
+                        )?;
+                    }
+                }
+
+                mb.rectangle(
+                    DrawMode::fill(),
+                    tile_rect(drag.to, offset),
+                    Color::new(0.0, 1.0, 1.0, 0.35),
+                )?;
+            }
+            None => {
+                mb.rectangle(
+                    DrawMode::stroke(3.0),
+                    tile_rect(drag.to, offset),
+                    Color::RED,
+                )?;
+            }
+        }
+
+        canvas.draw(&Mesh::from_data(ctx, mb.build()), DrawParam::default());
+
+        Ok(())
     }
 
-    fn draw_playing(&mut self, ctx: &Context, canvas: &mut Canvas) -> GameResult {
-        let level = self.get_current_level().unwrap();
+    // Where the level is drawn, in tiles, so that it is centered in the
+    // window
+    fn board_offset(&self) -> Point2D {
+        let level = self.game.level();
 
-        // Calculate offset to draw player and boxes relative to the level
-        let offset = Point2D {
+        Point2D {
             x: (WINDOW_WIDTH - level.width) / 2,
             y: (WINDOW_HEIGHT - level.height) / 2,
+        }
+    }
+
+    // Level tile shown at the given window position
+    fn tile_at(&self, x: f32, y: f32) -> Option<Point2D> {
+        let level = self.game.level();
+        let offset = self.board_offset();
+        let tile = Point2D {
+            x: (x / TILE_WIDTH as f32).floor() as i32 - offset.x,
+            y: (y / TILE_HEIGHT as f32).floor() as i32 - offset.y,
         };
 
-        level.draw(&self.sprites, canvas, offset);
-        self.player.draw(&self.sprites, canvas, offset);
+        level.get_tile(tile.x, tile.y).map(|_| tile)
+    }
+
+    // Appends the current position to a file in the user data directory,
+    // from where it can be shared or loaded as a level of its own.
+    fn save_position(&self, ctx: &Context) -> GameResult {
+        let mut file = ctx.fs.open_options(
+            "/positions.txt",
+            filesystem::OpenOptions::new().create(true).append(true),
+        )?;
 
-        for b in &self.boxes {
-            b.draw(&self.sprites, canvas, offset, &level.targets);
+        writeln!(file, "; {}\n", self.game.level().metadata.display_name())?;
+        writeln!(file, "{}", self.game.to_xsb())?;
+
+        Ok(())
+    }
+
+    fn request_hint(&mut self) {
+        if self.hint_request.is_some() {
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let game = self.game.clone();
+
+        std::thread::spawn(move || {
+            // Nobody is waiting anymore if the level was left in the meantime
+            let _ = sender.send(find_hint(&game, &HINT_LIMITS));
+        });
+
+        self.hint = None;
+        self.hint_request = Some((receiver, self.game.moves().to_vec()));
+        self.hints_used += 1;
+    }
+
+    // The hint for the current position, if there is one.
+    fn current_hint(&self) -> Option<Hint> {
+        match &self.hint {
+            Some((hint, moves)) if moves.as_slice() == self.game.moves() => Some(*hint),
+            _ => None,
         }
+    }
+
+    fn draw_hint(&self, ctx: &Context, canvas: &mut Canvas, offset: Point2D) -> GameResult {
+        let tile = |p: Point2D| tile_rect(p, offset);
+        let (text, color) = match (self.current_hint(), &self.hint_request) {
+            (_, Some(_)) => (String::from("Looking for a hint..."), Color::WHITE),
+            (Some(Hint::Push { from, direction }), _) => {
+                let mut mb = MeshBuilder::new();
 
-        let mut hint_texts: Vec<TextBlock> = Vec::new();
+                mb.rectangle(DrawMode::stroke(3.0), tile(from), Color::YELLOW)?;
+                mb.rectangle(
+                    DrawMode::stroke(3.0),
+                    tile(from + direction.delta()),
+                    Color::GREEN,
+                )?;
+                canvas.draw(&Mesh::from_data(ctx, mb.build()), DrawParam::default());
 
-        hint_texts.push(TextBlock::new(
-            TextFragment::new("Press BACKSPACE to undo last move")
+                (
+                    format!(
+                        "Hint: push the marked box {}",
+                        format!("{:?}", direction).to_lowercase()
+                    ),
+                    Color::YELLOW,
+                )
+            }
+            (Some(Hint::Undo { moves }), _) => (
+                format!("This position is unsolvable, undo {} moves", moves),
+                Color::RED,
+            ),
+            (Some(Hint::Solved), _) => (String::from("The level is solved"), Color::GREEN),
+            (Some(Hint::NotFound(e)), _) => (format!("No hint, {}", e), Color::RED),
+            (Some(Hint::Failed), _) => (String::from("No hint, the search failed"), Color::RED),
+            (None, None) => return Ok(()),
+        };
+
+        print_spaced(
+            ctx,
+            canvas,
+            &vec![TextBlock::new(
+                TextFragment::new(text)
+                    .font("Videotype")
+                    .color(color)
+                    .scale(20.0),
+                (0.0, 0.0, 0.0, 0.0),
+                TextAlign::Middle,
+            )],
+            Point2D {
+                x: WINDOW_WIDTH / 2 * TILE_WIDTH,
+                y: (WINDOW_HEIGHT - 1) * TILE_HEIGHT,
+            },
+        );
+
+        Ok(())
+    }
+
+    // Appends the moves made so far in LURD notation to a file in the user
+    // data directory. Entries name the level by its fingerprint, so they can
+    // be checked with `rustoban verify`.
+    fn save_solution(&self, ctx: &Context) -> GameResult {
+        let mut file = ctx.fs.open_options(
+            "/solutions.txt",
+            filesystem::OpenOptions::new().create(true).append(true),
+        )?;
+
+        writeln!(file, "; {}", self.game.level().metadata.display_name())?;
+        writeln!(file, "Level: {}", self.current_fingerprint())?;
+        writeln!(file, "{}\n", self.game.to_lurd())?;
+
+        Ok(())
+    }
+
+    // Brings the current screen up to date after the packs changed on disk.
+    // Collections and levels that are gone are replaced by the last ones
+    // left. The level being played is only started over if its layout
+    // changed, in which case the game goes on with the new one.
+    fn reload_level(&mut self) {
+        let last_collection = self.levels.num_collections() - 1;
+        let collection = self.current_collection.min(last_collection);
+        let num_levels = self.levels.get_collection(collection).unwrap().num_levels();
+        let level = self.current_level.min(num_levels - 1);
+        let changed = self.levels.get_fingerprint(collection, level)
+            != Some(&self.game.level().fingerprint());
+
+        self.selected_collection = self.selected_collection.min(last_collection);
+        self.selected_level = self.selected_level.min(num_levels - 1);
+        self.scroll_to_selection();
+        self.current_collection = collection;
+        self.current_level = level;
+
+        // The pack browser and the level select screen just show the new packs
+        let is_playing = !matches!(
+            self.game_state,
+            GameState::Browsing | GameState::LevelSelect
+        );
+
+        if is_playing && changed {
+            self.play_level(collection, level);
+        }
+    }
+
+    fn update_solved(&mut self, ctx: &Context) {
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Return)
+            || ctx.keyboard.is_key_just_pressed(KeyCode::NumpadEnter)
+        {
+            let collection = self.current_collection;
+            let num_levels = self.levels.get_collection(collection).unwrap().num_levels();
+
+            self.play_level(collection, (self.current_level + 1) % num_levels);
+        } else if ctx.keyboard.is_key_just_pressed(KeyCode::Escape) {
+            self.select_level(self.current_collection, self.current_level);
+        } else if ctx.keyboard.is_key_just_pressed(KeyCode::C) {
+            if let Err(e) = self.save_solution(ctx) {
+                eprintln!("Could not save solution: {}", e);
+            }
+        } else if ctx.keyboard.is_key_just_pressed(KeyCode::V) {
+            let solution = self.game.to_lurd();
+
+            // The solution was just played, so it can always be read back
+            self.replay(&solution).unwrap();
+        }
+    }
+
+    fn draw_browsing(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult {
+        let num_collections = self.levels.num_collections();
+        let mut blocks: Vec<TextBlock> = vec![TextBlock::new(
+            TextFragment::new("Choose a level pack")
+                .font("Videotype")
+                .color(Color::GREEN)
+                .scale(24.0),
+            (48.0, 0.0, 0.0, 0.0),
+            TextAlign::Middle,
+        )];
+
+        // Scroll the list so the selected pack is always visible
+        let first = self
+            .selected_collection
+            .saturating_sub(BROWSER_ROWS / 2)
+            .min(num_collections.saturating_sub(BROWSER_ROWS));
+
+        for idx in first..num_collections.min(first + BROWSER_ROWS) {
+            let collection = self.levels.get_collection(idx).unwrap();
+            let color = if idx == self.selected_collection {
+                Color::YELLOW
+            } else {
+                Color::WHITE
+            };
+
+            blocks.push(TextBlock::new(
+                TextFragment::new(format!(
+                    "{}  ({} levels, {}% solved)",
+                    collection.name,
+                    collection.num_levels(),
+                    self.completion(idx)
+                ))
                 .font("Videotype")
+                .color(color)
                 .scale(20.0),
-            (0.0, 0.0, 0.0, 0.0),
-            TextAlign::Begin,
-        ));
-        hint_texts.push(TextBlock::new(
-            TextFragment::new("Press R to reset level")
+                (16.0, 0.0, 0.0, 0.0),
+                TextAlign::Middle,
+            ));
+        }
+
+        blocks.push(TextBlock::new(
+            TextFragment::new("Use the arrow keys to choose and ENTER to play")
                 .font("Videotype")
                 .scale(20.0),
-            (10.0, 0.0, 0.0, 0.0),
-            TextAlign::Begin,
+            (48.0, 0.0, 0.0, 0.0),
+            TextAlign::Middle,
         ));
 
-        let mut move_texts: Vec<TextBlock> = Vec::new();
+        print_spaced(
+            ctx,
+            canvas,
+            &blocks,
+            Point2D {
+                x: WINDOW_WIDTH / 2 * TILE_WIDTH,
+                y: 0,
+            },
+        );
+
+        Ok(())
+    }
 
-        move_texts.push(TextBlock::new(
-            TextFragment::new(&format!("Moves: {}", self.moves.len()))
+    fn draw_level_select(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult {
+        let collection = self.levels.get_collection(self.current_collection).unwrap();
+        let first = self.first_row * GRID_COLUMNS;
+        let metric = self.progress.metric();
+        let mut frames = MeshBuilder::new();
+
+        print_spaced(
+            ctx,
+            canvas,
+            &vec![TextBlock::new(
+                TextFragment::new(format!(
+                    "{}  ({}% solved)",
+                    collection.name,
+                    self.completion(self.current_collection)
+                ))
+                .font("Videotype")
+                .color(Color::GREEN)
+                .scale(24.0),
+                (40.0, 0.0, 0.0, 0.0),
+                TextAlign::Middle,
+            )],
+            Point2D {
+                x: WINDOW_WIDTH / 2 * TILE_WIDTH,
+                y: 0,
+            },
+        );
+
+        for idx in first
+            ..collection
+                .num_levels()
+                .min(first + GRID_ROWS * GRID_COLUMNS)
+        {
+            let cell = self.grid_cell(idx).unwrap();
+            let area = Rect::new(
+                cell.x + (CELL_WIDTH - THUMBNAIL_WIDTH) / 2.0,
+                cell.y,
+                THUMBNAIL_WIDTH,
+                THUMBNAIL_HEIGHT,
+            );
+            let level = &collection.levels()[idx];
+            let best = collection
+                .get_fingerprint(idx)
+                .and_then(|fingerprint| self.progress.get_record(fingerprint));
+            let (label, color) = match best {
+                Some(record) => {
+                    let stats = record.best(metric);
+                    let count = match metric {
+                        Metric::Moves => stats.moves,
+                        Metric::Pushes => stats.pushes,
+                    };
+
+                    (
+                        format!("{} - best {} {}", idx + 1, count, metric.name()),
+                        Color::GREEN,
+                    )
+                }
+                None => (format!("{} - unsolved", idx + 1), Color::WHITE),
+            };
+
+            draw_thumbnail(level, ctx, canvas, area)?;
+
+            if idx == self.selected_level {
+                frames.rectangle(DrawMode::stroke(3.0), area, Color::YELLOW)?;
+            } else if best.is_some() {
+                frames.rectangle(DrawMode::stroke(2.0), area, Color::GREEN)?;
+            }
+
+            print_spaced(
+                ctx,
+                canvas,
+                &vec![TextBlock::new(
+                    TextFragment::new(label)
+                        .font("Videotype")
+                        .color(color)
+                        .scale(16.0),
+                    (THUMBNAIL_HEIGHT + 8.0, 0.0, 0.0, 0.0),
+                    TextAlign::Middle,
+                )],
+                Point2D {
+                    x: (cell.x + CELL_WIDTH / 2.0) as i32,
+                    y: cell.y as i32,
+                },
+            );
+        }
+
+        canvas.draw(&Mesh::from_data(ctx, frames.build()), DrawParam::default());
+
+        let mut hints = format!(
+            "Level {} of {}",
+            self.selected_level + 1,
+            collection.num_levels()
+        );
+
+        if self.num_rows() > GRID_ROWS {
+            hints.push_str(", scroll for more");
+        }
+
+        print_spaced(
+            ctx,
+            canvas,
+            &vec![
+                TextBlock::new(
+                    TextFragment::new(hints).font("Videotype").scale(20.0),
+                    (0.0, 0.0, 0.0, 0.0),
+                    TextAlign::Middle,
+                ),
+                TextBlock::new(
+                    TextFragment::new(
+                        "Press ENTER or click a level to play, ESC to choose another pack",
+                    )
+                    .font("Videotype")
+                    .scale(20.0),
+                    (10.0, 0.0, 0.0, 0.0),
+                    TextAlign::Middle,
+                ),
+                TextBlock::new(
+                    TextFragment::new(format!(
+                        "Solutions are ranked by {}, press M to change",
+                        metric.name()
+                    ))
+                    .font("Videotype")
+                    .scale(20.0),
+                    (10.0, 0.0, 0.0, 0.0),
+                    TextAlign::Middle,
+                ),
+            ],
+            Point2D {
+                x: WINDOW_WIDTH / 2 * TILE_WIDTH,
+                y: GRID_TOP as i32 + GRID_ROWS as i32 * CELL_HEIGHT as i32 + 16,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn draw_playing(&mut self, ctx: &Context, canvas: &mut Canvas) -> GameResult {
+        let level = self.game.level();
+
+        // Calculate offset to draw player and boxes relative to the level
+        let offset = self.board_offset();
+
+        draw_level(level, &self.sprites, canvas, offset);
+
+        if self.game_state == GameState::Playing {
+            self.draw_overlay(ctx, canvas, offset)?;
+        }
+
+        draw_player(
+            self.game.player(),
+            &self.sprites,
+            canvas,
+            offset,
+            self.animator.player_position(),
+        );
+
+        let deadlocked = self.game.deadlocked_boxes();
+
+        for b in self.game.boxes() {
+            draw_box(
+                &self.sprites,
+                canvas,
+                offset,
+                &level.targets,
+                deadlocked.contains(b.get_id()),
+                self.animator
+                    .box_position(b.get_id())
+                    .unwrap_or_else(|| (*b.get_position()).into()),
+            );
+        }
+
+        self.animator.draw(ctx, canvas, offset)?;
+
+        let hints: &[&str] = if self.game_state == GameState::Replaying {
+            &[
+                "Press SPACE to pause the replay",
+                "Press ESC to stop the replay and play on",
+            ]
+        } else {
+            &[
+                "Press BACKSPACE to undo last move",
+                "Press SHIFT+BACKSPACE to redo",
+                "Press R to reset level",
+                "Press S to save the position",
+                "Press C to save the moves as a solution",
+                "Press H for a hint",
+                "Press D to toggle safe mode",
+                "Click a tile to walk there",
+                "Drag a box to push it somewhere",
+                "Click a box or press SPACE to show where it can go",
+                "Press A to show where you can walk",
+                "Press ESC to choose another level",
+            ]
+        };
+        let hint_texts: Vec<TextBlock> = hints
+            .iter()
+            .enumerate()
+            .map(|(i, hint)| {
+                TextBlock::new(
+                    TextFragment::new(*hint).font("Videotype").scale(20.0),
+                    (if i == 0 { 0.0 } else { 10.0 }, 0.0, 0.0, 0.0),
+                    TextAlign::Begin,
+                )
+            })
+            .collect();
+
+        let stats = self.game.statistics();
+        let mut move_texts: Vec<TextBlock> = vec![
+            TextBlock::new(
+                TextFragment::new(format!("Moves: {}", stats.moves))
+                    .font("Videotype")
+                    .scale(20.0),
+                (0.0, 0.0, 0.0, 0.0),
+                TextAlign::End,
+            ),
+            TextBlock::new(
+                TextFragment::new(format!("Pushes: {}", stats.pushes))
+                    .font("Videotype")
+                    .scale(20.0),
+                (10.0, 0.0, 0.0, 0.0),
+                TextAlign::End,
+            ),
+            TextBlock::new(
+                TextFragment::new(format!("Time: {}", format_duration(self.started.elapsed())))
+                    .font("Videotype")
+                    .scale(20.0),
+                (10.0, 0.0, 0.0, 0.0),
+                TextAlign::End,
+            ),
+        ];
+
+        if self.game.safe_mode() {
+            move_texts.push(TextBlock::new(
+                TextFragment::new("Safe mode").font("Videotype").scale(20.0),
+                (10.0, 0.0, 0.0, 0.0),
+                TextAlign::End,
+            ));
+        }
+
+        let history = self.game.history();
+        let redo_len = history.redo_len();
+
+        if redo_len > 0 {
+            move_texts.push(TextBlock::new(
+                TextFragment::new(format!("Redo: {} moves", redo_len))
+                    .font("Videotype")
+                    .scale(20.0),
+                (10.0, 0.0, 0.0, 0.0),
+                TextAlign::End,
+            ));
+        }
+
+        // Alternative lines of play continue from here
+        if let (branch, branches @ 2..) = history.branches() {
+            move_texts.push(TextBlock::new(
+                TextFragment::new(format!("Branch {} of {} (TAB to switch)", branch, branches))
+                    .font("Videotype")
+                    .scale(20.0),
+                (10.0, 0.0, 0.0, 0.0),
+                TextAlign::End,
+            ));
+        }
+
+        let metadata = &level.metadata;
+        let mut info_texts: Vec<TextBlock> = vec![TextBlock::new(
+            TextFragment::new(metadata.display_name())
                 .font("Videotype")
                 .scale(20.0),
             (0.0, 0.0, 0.0, 0.0),
-            TextAlign::End,
-        ));
+            TextAlign::Begin,
+        )];
+
+        if let Some(author) = &metadata.author {
+            info_texts.push(TextBlock::new(
+                TextFragment::new(format!("by {}", author))
+                    .font("Videotype")
+                    .scale(16.0),
+                (6.0, 0.0, 0.0, 0.0),
+                TextAlign::Begin,
+            ));
+        }
+
+        if let Some(copyright) = &metadata.copyright {
+            info_texts.push(TextBlock::new(
+                TextFragment::new(format!("Copyright {}", copyright))
+                    .font("Videotype")
+                    .scale(16.0),
+                (6.0, 0.0, 0.0, 0.0),
+                TextAlign::Begin,
+            ));
+        }
+
+        if let Some(comment) = &metadata.comment {
+            info_texts.push(TextBlock::new(
+                TextFragment::new(comment.as_str())
+                    .font("Videotype")
+                    .scale(16.0),
+                (6.0, 0.0, 0.0, 0.0),
+                TextAlign::Begin,
+            ));
+        }
+
+        if let Some(replay) = &self.replay {
+            let (text, color) = match &replay.error {
+                Some(e) => (format!("Replay stopped, {}", e), Color::RED),
+                None if replay.next == replay.steps.len() && self.game.is_solved() => (
+                    String::from("Replay finished, the level is solved"),
+                    Color::GREEN,
+                ),
+                None if replay.next == replay.steps.len() => (
+                    String::from("Replay finished, the level is not solved"),
+                    Color::YELLOW,
+                ),
+                None => (
+                    format!(
+                        "Replay: move {} of {}{}",
+                        replay.next,
+                        replay.steps.len(),
+                        if replay.paused { " (paused)" } else { "" }
+                    ),
+                    Color::WHITE,
+                ),
+            };
+
+            print_spaced(
+                ctx,
+                canvas,
+                &vec![TextBlock::new(
+                    TextFragment::new(text)
+                        .font("Videotype")
+                        .color(color)
+                        .scale(20.0),
+                    (0.0, 0.0, 0.0, 0.0),
+                    TextAlign::Middle,
+                )],
+                Point2D {
+                    x: WINDOW_WIDTH / 2 * TILE_WIDTH,
+                    y: (WINDOW_HEIGHT - 1) * TILE_HEIGHT,
+                },
+            );
+        }
+
+        if self.game_state == GameState::Playing {
+            self.draw_drag(ctx, canvas, offset)?;
+            self.draw_hint(ctx, canvas, offset)?;
+        }
 
         print_spaced(ctx, canvas, &hint_texts, Point2D { x: 32, y: 24 });
+        print_spaced(
+            ctx,
+            canvas,
+            &info_texts,
+            Point2D {
+                x: 32,
+                y: (WINDOW_HEIGHT - 3) * TILE_HEIGHT,
+            },
+        );
         print_spaced(
             ctx,
             canvas,
@@ -179,63 +1330,146 @@ impl State {
             (48.0, 0.0, 0.0, 0.0),
             TextAlign::Middle,
         ));
+        let stats = self.game.statistics();
+
         blocks.push(TextBlock::new(
-            TextFragment::new(&format!("Number of Moves: {}", self.moves.len()))
+            TextFragment::new(format!("Number of Moves: {}", stats.moves))
                 .font("Videotype")
                 .scale(48.0),
             (96.0, 0.0, 0.0, 0.0),
             TextAlign::Middle,
         ));
         blocks.push(TextBlock::new(
-            TextFragment::new("Press ENTER to play the next level").font("Videotype"),
-            (96.0, 0.0, 0.0, 0.0),
+            TextFragment::new(format!(
+                "Pushes: {}  Box changes: {}  Lines: {}  Time: {}",
+                stats.pushes,
+                stats.box_changes,
+                stats.player_lines,
+                format_duration(self.solve_time)
+            ))
+            .font("Videotype")
+            .scale(24.0),
+            (16.0, 0.0, 0.0, 0.0),
             TextAlign::Middle,
         ));
 
-        print_spaced(ctx, canvas, &blocks, origin);
-
-        Ok(())
-    }
+        blocks.push(TextBlock::new(
+            TextFragment::new(format!("Hints used: {}", self.hints_used))
+                .font("Videotype")
+                .scale(24.0),
+            (16.0, 0.0, 0.0, 0.0),
+            TextAlign::Middle,
+        ));
 
-    fn undo_last_move(&mut self) {
-        if let Some(last_move) = self.moves.pop() {
-            self.player.position -= last_move.delta;
+        if self.new_record {
+            blocks.push(TextBlock::new(
+                TextFragment::new("New record!")
+                    .font("Videotype")
+                    .color(Color::YELLOW)
+                    .scale(24.0),
+                (32.0, 0.0, 0.0, 0.0),
+                TextAlign::Middle,
+            ));
+        } else if self.replayed {
+            blocks.push(TextBlock::new(
+                TextFragment::new("Replayed solutions don't count as records")
+                    .font("Videotype")
+                    .scale(24.0),
+                (32.0, 0.0, 0.0, 0.0),
+                TextAlign::Middle,
+            ));
+        }
 
-            if let Some(id) = last_move.box_id {
-                let b = self.boxes.iter_mut().find(|b| *b.get_id() == id).unwrap();
+        if let Some(best) = self.progress.get_record(self.current_fingerprint()) {
+            let texts = [
+                format!("Best by moves: {}", best.by_moves),
+                format!("Best by pushes: {}", best.by_pushes),
+                format!("Best time: {}", format_duration(best.time)),
+            ];
 
-                *b.get_position_mut() -= last_move.delta;
+            for text in texts {
+                blocks.push(TextBlock::new(
+                    TextFragment::new(text).font("Videotype").scale(20.0),
+                    (10.0, 0.0, 0.0, 0.0),
+                    TextAlign::Middle,
+                ));
             }
         }
-    }
-
-    fn reset_level(&mut self) {
-        let level = self.get_current_level().unwrap();
-        let mut boxes: Vec<MovableBox> = Vec::new();
+        blocks.push(TextBlock::new(
+            TextFragment::new("Press ENTER to play the next level").font("Videotype"),
+            (96.0, 0.0, 0.0, 0.0),
+            TextAlign::Middle,
+        ));
+        blocks.push(TextBlock::new(
+            TextFragment::new("Press ESC to choose another level")
+                .font("Videotype")
+                .scale(20.0),
+            (16.0, 0.0, 0.0, 0.0),
+            TextAlign::Middle,
+        ));
+        blocks.push(TextBlock::new(
+            TextFragment::new("Press C to save the solution, V to watch it again")
+                .font("Videotype")
+                .scale(20.0),
+            (10.0, 0.0, 0.0, 0.0),
+            TextAlign::Middle,
+        ));
 
-        for pos in &level.boxes {
-            boxes.push(MovableBox::new(*pos));
-        }
+        print_spaced(ctx, canvas, &blocks, origin);
 
-        self.player = Player::new(level.player);
-        self.boxes = boxes;
-        self.moves.clear();
+        Ok(())
     }
 }
 
 impl EventHandler for State {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        let level = self.get_current_level().unwrap();
+        match self.levels.reload_if_changed() {
+            Ok(true) => self.reload_level(),
+            Ok(false) => (),
+            Err(e) => eprintln!("Could not reload levels: {}", e),
+        }
+
+        if let Some((receiver, moves)) = &self.hint_request {
+            match receiver.try_recv() {
+                Ok(hint) => {
+                    self.hint = Some((hint, moves.clone()));
+                    self.hint_request = None;
+                }
+                Err(TryRecvError::Empty) => (),
+                Err(TryRecvError::Disconnected) => {
+                    eprintln!("The search for a hint stopped without an answer");
+                    self.hint = Some((Hint::Failed, moves.clone()));
+                    self.hint_request = None;
+                }
+            }
+        }
+
+        if self.game_state == GameState::Playing && self.game.is_solved() {
+            let record = Record::new(self.game.statistics(), self.started.elapsed());
 
-        if level.is_solved(&self.boxes) {
+            self.solve_time = record.time;
+            let fingerprint = self.current_fingerprint().to_owned();
+
+            self.new_record = !self.replayed && self.progress.add_record(&fingerprint, record);
             self.game_state = GameState::Solved;
+            self.animator.celebrate();
         }
 
         match self.game_state {
+            GameState::Browsing => self.update_browsing(ctx),
+            GameState::LevelSelect => self.update_level_select(ctx),
             GameState::Playing => self.update_playing(ctx),
+            GameState::Replaying => self.update_replaying(ctx),
             GameState::Solved => self.update_solved(ctx),
         }
 
+        self.animator
+            .update(&self.game, ctx.time.delta().as_secs_f32());
+
+        if let Err(e) = self.progress.save(ctx) {
+            eprintln!("Could not save progress: {}", e);
+        }
+
         Ok(())
     }
 
@@ -243,7 +1477,11 @@ impl EventHandler for State {
         let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
 
         match self.game_state {
-            GameState::Playing => self.draw_playing(ctx, &mut canvas)?,
+            GameState::Browsing => self.draw_browsing(ctx, &mut canvas)?,
+            GameState::LevelSelect => self.draw_level_select(ctx, &mut canvas)?,
+            GameState::Playing | GameState::Replaying => self.draw_playing(ctx, &mut canvas)?,
+            // The board stays in view until the celebration is over
+            GameState::Solved if self.animator.is_busy() => self.draw_playing(ctx, &mut canvas)?,
             GameState::Solved => self.draw_solved(ctx, &mut canvas)?,
         }
 
@@ -251,4 +1489,56 @@ impl EventHandler for State {
 
         Ok(())
     }
+
+    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, repeated: bool) -> GameResult {
+        // Held arrow keys are repeated by the input queue at its own rate
+        if repeated {
+            return Ok(());
+        }
+
+        match input.keycode {
+            // ESC goes back a screen everywhere else
+            Some(KeyCode::Escape) if self.game_state == GameState::Browsing => ctx.request_quit(),
+            Some(keycode) if self.game_state == GameState::Playing => {
+                if let Some(direction) = InputQueue::direction(keycode) {
+                    self.input.press(direction, Instant::now());
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut Context, input: KeyInput) -> GameResult {
+        if let Some(direction) = input.keycode.and_then(InputQueue::direction) {
+            self.input.release(direction, Instant::now());
+        }
+
+        Ok(())
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) -> GameResult {
+        if self.game_state == GameState::LevelSelect {
+            let last_row = self.num_rows().saturating_sub(GRID_ROWS);
+
+            if y > 0.0 {
+                self.first_row = self.first_row.saturating_sub(1);
+            } else if y < 0.0 {
+                self.first_row = (self.first_row + 1).min(last_row);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The window area of a level tile, with the level drawn at `offset`
+fn tile_rect(p: Point2D, offset: Point2D) -> Rect {
+    Rect::new(
+        (TILE_WIDTH * (p.x + offset.x)) as f32,
+        (TILE_HEIGHT * (p.y + offset.y)) as f32,
+        TILE_WIDTH as f32,
+        TILE_HEIGHT as f32,
+    )
 }
@@ -0,0 +1,79 @@
+use crate::prelude::*;
+
+// The two ways solutions are usually ranked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Metric {
+    Moves,
+    Pushes,
+}
+
+impl Metric {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Moves => "moves",
+            Metric::Pushes => "pushes",
+        }
+    }
+}
+
+// Counts describing a line of play. A box change is a push of a different
+// box than the one pushed before, a player line a straight run of moves in
+// one direction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Statistics {
+    pub moves: usize,
+    pub pushes: usize,
+    pub box_changes: usize,
+    pub player_lines: usize,
+}
+
+impl Statistics {
+    pub fn from_moves(moves: &[PlayerMove]) -> Self {
+        let mut stats = Self::default();
+        let mut last_box: Option<Uuid> = None;
+        let mut last_delta: Option<Point2D> = None;
+
+        for player_move in moves {
+            stats.moves += 1;
+
+            if let Some(id) = player_move.box_id {
+                stats.pushes += 1;
+
+                if last_box != Some(id) {
+                    stats.box_changes += 1;
+                    last_box = Some(id);
+                }
+            }
+
+            if last_delta != Some(player_move.delta) {
+                stats.player_lines += 1;
+                last_delta = Some(player_move.delta);
+            }
+        }
+
+        stats
+    }
+
+    // Compares by the given metric first, then by the other one, then by
+    // box changes and player lines.
+    pub fn is_better_than(&self, other: &Statistics, metric: Metric) -> bool {
+        self.rank(metric) < other.rank(metric)
+    }
+
+    fn rank(&self, metric: Metric) -> (usize, usize, usize, usize) {
+        match metric {
+            Metric::Moves => (self.moves, self.pushes, self.box_changes, self.player_lines),
+            Metric::Pushes => (self.pushes, self.moves, self.box_changes, self.player_lines),
+        }
+    }
+}
+
+impl std::fmt::Display for Statistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} moves, {} pushes, {} box changes, {} lines",
+            self.moves, self.pushes, self.box_changes, self.player_lines
+        )
+    }
+}
@@ -0,0 +1,46 @@
+pub mod game;
+pub mod utils;
+
+// Everything about levels and the rules. None of it needs ggez, so it can be
+// used without opening a window.
+pub mod prelude {
+    pub use crate::game::hint::*;
+    pub use crate::game::history::*;
+    pub use crate::game::level::*;
+    pub use crate::game::lurd::*;
+    pub use crate::game::movable_box::*;
+    pub use crate::game::player::*;
+    pub use crate::game::rules::*;
+    pub use crate::game::solver::*;
+    pub use crate::game::statistics::*;
+    pub use crate::utils::*;
+    pub use uuid::Uuid;
+}
+
+// The prelude plus the parts of the game that draw, handle input or store
+// progress, and the parts of ggez they need.
+#[cfg(feature = "gfx")]
+pub mod gfx_prelude {
+    pub use crate::game::gfx::animation::*;
+    pub use crate::game::gfx::board::*;
+    pub use crate::game::gfx::ui::*;
+    pub use crate::game::gfx::*;
+    pub use crate::game::input_queue::*;
+    pub use crate::game::progress::*;
+    pub use crate::game::state::*;
+    pub use crate::prelude::*;
+    pub use ggez::conf::{WindowMode, WindowSetup};
+    pub use ggez::event::{self, EventHandler};
+    pub use ggez::graphics::*;
+    pub use ggez::input::keyboard::{KeyCode, KeyInput, KeyMods};
+    pub use ggez::input::mouse::MouseButton;
+    pub use ggez::*;
+
+    // In pixels
+    pub const TILE_WIDTH: i32 = 32;
+    pub const TILE_HEIGHT: i32 = 32;
+
+    // In tiles
+    pub const WINDOW_WIDTH: i32 = 40;
+    pub const WINDOW_HEIGHT: i32 = 25;
+}
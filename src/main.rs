@@ -1,34 +1,162 @@
-mod game;
-mod utils;
-
-mod prelude {
-    pub use crate::game::gfx::ui::*;
-    pub use crate::game::gfx::*;
-    pub use crate::game::level::*;
-    pub use crate::game::movable_box::*;
-    pub use crate::game::player::*;
-    pub use crate::game::*;
-    pub use crate::state::*;
-    pub use crate::utils::*;
-    pub use ggez::conf::{WindowMode, WindowSetup};
-    pub use ggez::event::{self, EventHandler, EventLoop};
-    pub use ggez::graphics::*;
-    pub use ggez::input::keyboard::KeyCode;
-    pub use ggez::*;
-    pub use uuid::Uuid;
-
-    // In pixels
-    pub const TILE_WIDTH: i32 = 32;
-    pub const TILE_HEIGHT: i32 = 32;
-
-    // In tiles
-    pub const WINDOW_WIDTH: i32 = 40;
-    pub const WINDOW_HEIGHT: i32 = 25;
-}
-
-use prelude::*;
+#[cfg(feature = "gfx")]
+use rustoban::gfx_prelude::*;
+#[cfg(not(feature = "gfx"))]
+use rustoban::prelude::*;
+use std::collections::HashSet;
+use std::path::Path;
+#[cfg(feature = "gfx")]
+use std::path::PathBuf;
+use std::time::Duration;
+
+const USAGE: &str = "Usage: rustoban [play] [PACK...] [--level NUMBER|TITLE] [--watch]
+                        [--replay LURD|FILE] [--speed TILES]
+                        [--repeat-delay MILLISECONDS] [--repeat-rate KEYS]
+       rustoban verify PACK SOLUTIONS
+       rustoban solve PACK [--level NUMBER|TITLE] [--time SECONDS]
+                           [--memory MEGABYTES]
+
+Plays the given level packs (XSB, RLE or SLC files, or directories
+containing them). Without a pack the bundled levels are played.
+
+The verify command checks that every level of PACK is solved by one of
+the LURD solutions in SOLUTIONS, without opening a window. It exits with
+an error if a solution is invalid or a level has no valid solution.
+
+The solve command finds solutions with the fewest pushes for the levels
+of PACK, or just the one given by --level, and writes them in the format
+read by verify. Each level may take up to --time seconds (10 by default)
+and about --memory megabytes (512 by default).
+
+Options:
+    -l, --level NUMBER|TITLE    Start at the level with this number
+                                (starting at 1) or title
+    -w, --watch                 Reload the packs whenever they change
+    -r, --replay LURD|FILE      Play back a solution in LURD notation,
+                                given directly or in a file
+    -s, --speed TILES           Tiles per second the player walks at (12 by
+                                default, 0 turns animations off)
+    --repeat-delay MILLISECONDS How long an arrow key is held before it
+                                repeats (250 by default, at most 10000)
+    --repeat-rate KEYS          Moves per second while an arrow key is held
+                                (12 by default, at most 100, 0 turns
+                                repeating off)
+    -h, --help                  Show this message";
+
+#[cfg(feature = "gfx")]
+struct PlayOptions {
+    packs: Vec<PathBuf>,
+    level: Option<String>,
+    watch: bool,
+    replay: Option<String>,
+    speed: Option<f32>,
+    repeat_delay: Option<u64>,
+    repeat_rate: Option<f32>,
+}
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if let Err(e) = run(&args) {
+        eprintln!("rustoban: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("-h") | Some("--help") | Some("help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        #[cfg(feature = "gfx")]
+        Some("play") => play(parse_play_options(&args[1..])?),
+        Some("verify") => verify(&args[1..]),
+        Some("solve") => solve_pack(&args[1..]),
+        #[cfg(feature = "gfx")]
+        _ => play(parse_play_options(args)?),
+        // Built with --no-default-features
+        #[cfg(not(feature = "gfx"))]
+        _ => Err(format!(
+            "this build can only verify and solve, playing needs the gfx feature\n\n{}",
+            USAGE
+        )),
+    }
+}
+
+#[cfg(feature = "gfx")]
+fn parse_play_options(args: &[String]) -> Result<PlayOptions, String> {
+    let mut options = PlayOptions {
+        packs: Vec::new(),
+        level: None,
+        watch: false,
+        replay: None,
+        speed: None,
+        repeat_delay: None,
+        repeat_rate: None,
+    };
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-l" | "--level" => match args.next() {
+                Some(level) => options.level = Some(level.clone()),
+                None => return Err(format!("{} needs a level number or title", arg)),
+            },
+            "-w" | "--watch" => options.watch = true,
+            "-r" | "--replay" => match args.next() {
+                Some(solution) => options.replay = Some(solution.clone()),
+                None => return Err(format!("{} needs a solution or file", arg)),
+            },
+            "-s" | "--speed" => {
+                let speed: f32 = parse_number(arg, args.next())?;
+
+                if !speed.is_finite() || speed < 0.0 {
+                    return Err(format!("{} needs a number of at least 0", arg));
+                }
+
+                options.speed = Some(speed);
+            }
+            "--repeat-delay" => {
+                let delay: u64 = parse_number(arg, args.next())?;
+
+                if delay > MAX_REPEAT_DELAY.as_millis() as u64 {
+                    return Err(format!(
+                        "{} needs a number from 0 to {}",
+                        arg,
+                        MAX_REPEAT_DELAY.as_millis()
+                    ));
+                }
+
+                options.repeat_delay = Some(delay);
+            }
+            "--repeat-rate" => {
+                let rate: f32 = parse_number(arg, args.next())?;
+
+                if !(0.0..=MAX_REPEAT_RATE).contains(&rate) {
+                    return Err(format!(
+                        "{} needs a number from 0 to {}",
+                        arg, MAX_REPEAT_RATE
+                    ));
+                }
+
+                options.repeat_rate = Some(rate);
+            }
+            _ if arg.starts_with("--level=") => {
+                options.level = Some(arg["--level=".len()..].to_owned())
+            }
+            _ if arg.starts_with('-') => {
+                return Err(format!("unknown option '{}'\n\n{}", arg, USAGE))
+            }
+            _ => options.packs.push(PathBuf::from(arg)),
+        }
+    }
+
+    Ok(options)
+}
+
+#[cfg(feature = "gfx")]
+fn play(options: PlayOptions) -> Result<(), String> {
+    let mut levels = LevelManager::new();
     let resource_dir = if let Ok(manifest_dir) = std::env::var("CARGO_MANIFEST_DIR") {
         let mut path = std::path::PathBuf::from(manifest_dir);
         path.push("resources");
@@ -37,6 +165,36 @@ fn main() {
         std::path::PathBuf::from("./resources")
     };
 
+    // Watching needs a real file, so the bundled levels are read from disk
+    // instead of through ggez in that case.
+    let mut packs = options.packs;
+
+    if packs.is_empty() && options.watch {
+        packs.push(resource_dir.join("levels.txt"));
+    }
+
+    // Packs given on the command line are read before the window opens, so
+    // that broken files don't flash an empty window.
+    for pack in &packs {
+        levels.load_from_path(pack).map_err(|e| e.to_string())?;
+    }
+
+    if options.watch {
+        levels.watch();
+    }
+
+    let solution = match &options.replay {
+        Some(replay) if PathBuf::from(replay).is_file() => {
+            Some(std::fs::read_to_string(replay).map_err(|e| format!("{}: {}", replay, e))?)
+        }
+        Some(replay) => Some(replay.clone()),
+        None => None,
+    };
+
+    if let Some(solution) = &solution {
+        parse_lurd(solution).map_err(|e| format!("invalid solution: {}", e))?;
+    }
+
     let (mut ctx, event_loop) = ContextBuilder::new("Rustoban", "Mirko Förster")
         .window_setup(WindowSetup::default().title("Rustoban"))
         .window_mode(
@@ -49,9 +207,232 @@ fn main() {
         )
         .add_resource_path(resource_dir)
         .build()
-        .expect("Could not create ggez context!");
+        .map_err(|e| format!("could not create ggez context: {}", e))?;
+
+    if packs.is_empty() {
+        levels
+            .load_from_file(&ctx, "/levels.txt")
+            .map_err(|e| format!("/levels.txt: {}", e))?;
+    }
+
+    for group in levels.find_duplicates() {
+        let names: Vec<String> = group
+            .iter()
+            .map(|(collection, idx)| level_name(&levels, *collection, *idx))
+            .collect();
+
+        eprintln!(
+            "rustoban: warning: these levels are the same, up to rotation and mirroring: {}",
+            names.join(", ")
+        );
+    }
+
+    let start = match &options.level {
+        Some(level) => Some(
+            levels
+                .find_level(level)
+                .ok_or_else(|| format!("there is no level '{}'", level))?,
+        ),
+        None => None,
+    };
+
+    let mut state = State::new(&mut ctx, levels, start)
+        .map_err(|e| format!("could not initialize game state: {}", e))?;
+
+    if let Some(speed) = options.speed {
+        state.set_animation_speed(speed);
+    }
 
-    let state = State::new(&mut ctx).expect("Could not initialize game state!");
+    state.set_key_repeat(KeyRepeat::new(
+        options
+            .repeat_delay
+            .map_or(DEFAULT_REPEAT_DELAY, Duration::from_millis),
+        options.repeat_rate.unwrap_or(DEFAULT_REPEAT_RATE),
+    ));
+
+    if let Some(solution) = &solution {
+        state
+            .replay(solution)
+            .map_err(|e| format!("invalid solution: {}", e))?;
+    }
 
     event::run(ctx, event_loop, state);
 }
+
+fn verify(args: &[String]) -> Result<(), String> {
+    let [pack, solutions] = args else {
+        return Err(format!(
+            "verify needs a pack and a solutions file\n\n{}",
+            USAGE
+        ));
+    };
+
+    let mut levels = LevelManager::new();
+
+    levels
+        .load_from_path(Path::new(pack))
+        .map_err(|e| e.to_string())?;
+
+    let contents =
+        std::fs::read_to_string(solutions).map_err(|e| format!("{}: {}", solutions, e))?;
+    let mut solved: HashSet<(usize, usize)> = HashSet::new();
+    let mut invalid = 0;
+
+    for entry in parse_solutions(&contents) {
+        let found = levels
+            .find_fingerprint(&entry.level)
+            .or_else(|| levels.find_level(&entry.level));
+
+        let Some((collection, idx)) = found else {
+            println!(
+                "{}, line {}: there is no level '{}'",
+                solutions, entry.line, entry.level
+            );
+            invalid += 1;
+            continue;
+        };
+
+        let name = level_name(&levels, collection, idx);
+
+        match verify_solution(levels.get_level(collection, idx).unwrap(), &entry.solution) {
+            Ok(game) => {
+                println!("{}: valid, {}", name, game.statistics());
+                solved.insert((collection, idx));
+            }
+            Err(e) => {
+                println!("{}: invalid, {}", name, e);
+                invalid += 1;
+            }
+        }
+    }
+
+    let mut total = 0;
+
+    for collection in 0..levels.num_collections() {
+        for idx in 0..levels.get_collection(collection).unwrap().num_levels() {
+            total += 1;
+
+            if !solved.contains(&(collection, idx)) {
+                println!(
+                    "{}: no valid solution",
+                    level_name(&levels, collection, idx)
+                );
+            }
+        }
+    }
+
+    println!("{} of {} levels solved", solved.len(), total);
+
+    if invalid > 0 || solved.len() < total {
+        return Err(format!(
+            "{} invalid solutions, {} levels without a valid solution",
+            invalid,
+            total - solved.len()
+        ));
+    }
+
+    Ok(())
+}
+
+fn solve_pack(args: &[String]) -> Result<(), String> {
+    let mut pack: Option<&String> = None;
+    let mut level: Option<&String> = None;
+    let mut limits = SolverLimits::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-l" | "--level" => {
+                level = Some(
+                    args.next()
+                        .ok_or_else(|| format!("{} needs a level number or title", arg))?,
+                )
+            }
+            "-t" | "--time" => {
+                let secs: f64 = parse_number(arg, args.next())?;
+                let time = Duration::try_from_secs_f64(secs).map_err(|_| {
+                    format!(
+                        "{} needs a number of seconds that is at least 0 and not too large",
+                        arg
+                    )
+                })?;
+
+                limits.time = Some(time);
+            }
+            "-m" | "--memory" => {
+                let megabytes: usize = parse_number(arg, args.next())?;
+                let bytes = megabytes
+                    .checked_mul(1024 * 1024)
+                    .ok_or_else(|| format!("{} needs a smaller number of megabytes", arg))?;
+
+                limits.memory = Some(bytes);
+            }
+            _ if arg.starts_with('-') => {
+                return Err(format!("unknown option '{}'\n\n{}", arg, USAGE))
+            }
+            _ if pack.is_none() => pack = Some(arg),
+            _ => return Err(format!("solve takes a single pack\n\n{}", USAGE)),
+        }
+    }
+
+    let pack = pack.ok_or_else(|| format!("solve needs a pack\n\n{}", USAGE))?;
+    let mut levels = LevelManager::new();
+
+    levels
+        .load_from_path(Path::new(pack))
+        .map_err(|e| e.to_string())?;
+
+    let selected: Vec<(usize, usize)> = match level {
+        Some(level) => vec![levels
+            .find_level(level)
+            .ok_or_else(|| format!("there is no level '{}'", level))?],
+        None => (0..levels.num_collections())
+            .flat_map(|c| (0..levels.get_collection(c).unwrap().num_levels()).map(move |l| (c, l)))
+            .collect(),
+    };
+    let mut failed = 0;
+
+    for (collection, idx) in selected {
+        let name = level_name(&levels, collection, idx);
+        let level = levels.get_level(collection, idx).unwrap();
+
+        match solve(level, &limits) {
+            Ok(solution) => {
+                let stats = verify_solution(level, &solution.lurd)
+                    .map_err(|e| format!("{}: the solver made a mistake, {}", name, e))?
+                    .statistics();
+
+                println!("; {}: {}", name, stats);
+                println!("Level: {}", level.fingerprint());
+                println!("{}\n", solution.lurd);
+            }
+            Err(e) => {
+                println!("; {}: {}\n", name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} levels could not be solved", failed));
+    }
+
+    Ok(())
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: Option<&String>) -> Result<T, String> {
+    value
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| format!("{} needs a number", option))
+}
+
+// Names a level by its pack and number, plus its title if it has one.
+fn level_name(levels: &LevelManager, collection: usize, idx: usize) -> String {
+    let pack = levels.get_collection(collection).unwrap();
+    let name = format!("{} #{}", pack.name, idx + 1);
+
+    match pack.levels()[idx].metadata.name() {
+        Some(title) if *title != (idx + 1).to_string() => format!("{} ({})", name, title),
+        _ => name,
+    }
+}
@@ -1,7 +1,6 @@
-use crate::mint::Point2;
-use crate::prelude::*;
+use std::time::Duration;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Point2D {
     pub x: i32,
     pub y: i32,
@@ -47,18 +46,9 @@ impl std::ops::SubAssign for Point2D {
     }
 }
 
-impl Into<Point2<f32>> for Point2D {
-    fn into(self) -> Point2<f32> {
-        Point2 {
-            x: self.x as f32,
-            y: self.y as f32,
-        }
-    }
-}
+// Formats a duration as minutes and seconds, e.g. "2:05"
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
 
-pub fn get_scaling_factors(image: &Image) -> [f32; 2] {
-    [
-        TILE_WIDTH as f32 / image.width() as f32,
-        TILE_HEIGHT as f32 / image.height() as f32,
-    ]
+    format!("{}:{:02}", secs / 60, secs % 60)
 }